@@ -0,0 +1,99 @@
+//! Measures how long it takes to enumerate, open, read from and write to devices, to quantify
+//! performance on real hardware.
+
+use crate::{check_serial_number_if_some, config, CliError, CliResult};
+use std::time::{Duration, Instant};
+
+/// A set of timings for a single operation.
+#[derive(Debug, Default)]
+pub struct Timings(Vec<Duration>);
+
+impl Timings {
+    /// Runs the operation, recording how long it took.
+    pub fn measure<T>(&mut self, operation: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = operation();
+        self.0.push(start.elapsed());
+        result
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "no samples".to_string();
+        }
+
+        let total: Duration = self.0.iter().sum();
+        let mean = total / self.0.len() as u32;
+        let mut sorted = self.0.clone();
+        sorted.sort();
+        let p95 = sorted[(sorted.len() * 95).div_ceil(100) - 1];
+
+        format!(
+            "min {:.2?}, mean {:.2?}, p95 {:.2?}, max {:.2?}",
+            sorted[0],
+            mean,
+            p95,
+            sorted[sorted.len() - 1]
+        )
+    }
+}
+
+pub fn handle_bench_command(
+    options: &config::GlobalOptions,
+    serial_number: Option<&str>,
+    iterations: u32,
+) -> CliResult {
+    let mut context = config::new_context(options)?;
+
+    let mut enumerate = Timings::default();
+    for _ in 0..iterations {
+        enumerate.measure(|| context.refresh_connected_devices())?;
+    }
+    println!(
+        "Enumerate ({} iterations): {}",
+        iterations,
+        enumerate.summary()
+    );
+
+    let devices: Vec<_> = context
+        .get_connected_devices()
+        .filter(check_serial_number_if_some(serial_number))
+        .collect();
+    if devices.is_empty() {
+        return Err(CliError::DeviceNotFound);
+    }
+
+    for device in devices {
+        println!(
+            "- {} ({}):",
+            device.device_type(),
+            device.serial_number().unwrap_or("")
+        );
+
+        let mut open = Timings::default();
+        let mut read = Timings::default();
+        let mut write = Timings::default();
+
+        for _ in 0..iterations {
+            let device_handle = open.measure(|| device.open(&context))?;
+            // Writing the current power state back to the device means the benchmark doesn't
+            // visibly change anything.
+            let is_on = read.measure(|| device_handle.is_on())?;
+            write.measure(|| device_handle.set_on(is_on))?;
+        }
+
+        println!("  - Open: {}", open.summary());
+        println!("  - Read: {}", read.summary());
+        println!("  - Write: {}", write.summary());
+    }
+
+    Ok(())
+}
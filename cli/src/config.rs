@@ -0,0 +1,456 @@
+//! The optional configuration file, read from `litra/config.toml` in the user's configuration
+//! directory (e.g. `~/.config/litra/config.toml` on Linux), or from the path in the
+//! `LITRA_CONFIG` environment variable.
+
+use crate::CliError;
+use litra::mock::MockDevice;
+use litra::{DeviceDescriptor, DeviceType, Litra, RetryPolicy};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const CONFIG_PATH_ENVIRONMENT_VARIABLE: &str = "LITRA_CONFIG";
+
+/// Options given on the command line which apply to every command. These are passed to each
+/// command's handler, which passes them on to [`new_context`].
+#[derive(Debug, Clone)]
+pub struct GlobalOptions {
+    pub timeout: Duration,
+    pub retries: u32,
+    /// Whether commands changing several devices should stop at the first device which fails,
+    /// rather than carrying on with the others.
+    pub strict: bool,
+    /// Simulated devices to list alongside connected ones, added by `litra emulate`.
+    pub simulated_devices: Vec<MockDevice>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Additional devices to support, on top of those built into the library.
+    #[serde(default)]
+    pub devices: Vec<DeviceConfig>,
+    /// Shorthands for commands, e.g. `meeting = "brightness --percentage 75"`.
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeviceConfig {
+    pub product_id: u16,
+    #[serde(default)]
+    pub model: Model,
+    pub feature_index: u8,
+    /// Defaults to the minimum brightness of the model.
+    pub minimum_brightness_in_lumen: Option<u16>,
+    /// Defaults to the maximum brightness of the model.
+    pub maximum_brightness_in_lumen: Option<u16>,
+    /// Defaults to the minimum temperature of the model.
+    pub minimum_temperature_in_kelvin: Option<u16>,
+    /// Defaults to the maximum temperature of the model.
+    pub maximum_temperature_in_kelvin: Option<u16>,
+}
+
+/// The model which a device from the configuration file is treated as, or which `litra emulate`
+/// simulates.
+#[derive(Debug, Default, Clone, Copy, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[clap(rename_all = "snake_case")]
+pub enum Model {
+    Glow,
+    #[default]
+    Beam,
+    BeamLx,
+}
+
+impl From<Model> for DeviceType {
+    fn from(model: Model) -> Self {
+        match model {
+            Model::Glow => DeviceType::LitraGlow,
+            Model::Beam => DeviceType::LitraBeam,
+            Model::BeamLx => DeviceType::LitraBeamLX,
+        }
+    }
+}
+
+impl From<&DeviceConfig> for DeviceDescriptor {
+    fn from(device: &DeviceConfig) -> Self {
+        let device_type = DeviceType::from(device.model);
+        DeviceDescriptor {
+            product_id: device.product_id,
+            device_type,
+            feature_index: device.feature_index,
+            minimum_brightness_in_lumen: device
+                .minimum_brightness_in_lumen
+                .unwrap_or_else(|| device_type.minimum_brightness_in_lumen()),
+            maximum_brightness_in_lumen: device
+                .maximum_brightness_in_lumen
+                .unwrap_or_else(|| device_type.maximum_brightness_in_lumen()),
+            minimum_temperature_in_kelvin: device
+                .minimum_temperature_in_kelvin
+                .unwrap_or_else(|| device_type.minimum_temperature_in_kelvin()),
+            maximum_temperature_in_kelvin: device
+                .maximum_temperature_in_kelvin
+                .unwrap_or_else(|| device_type.maximum_temperature_in_kelvin()),
+        }
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    match std::env::var_os(CONFIG_PATH_ENVIRONMENT_VARIABLE) {
+        Some(path) => Some(PathBuf::from(path)),
+        None => dirs::config_dir().map(|directory| directory.join("litra").join("config.toml")),
+    }
+}
+
+/// Loads the configuration file. A missing file is treated as an empty configuration.
+pub fn load_config() -> Result<Config, CliError> {
+    let Some(path) = config_path() else {
+        return Ok(Config::default());
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents).map_err(|error| {
+            CliError::InvalidConfig(path.display().to_string(), error.to_string())
+        }),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(error) => Err(CliError::InvalidConfig(
+            path.display().to_string(),
+            error.to_string(),
+        )),
+    }
+}
+
+/// Checks the configuration for problems which parsing alone doesn't catch, returning a description
+/// of each one along with the key it was found at. `check_command` is given the arguments an alias
+/// expands to, and returns an error if they aren't a valid built-in command.
+pub fn validate_config(
+    config: &Config,
+    is_built_in_command: impl Fn(&str) -> bool,
+    check_command: impl Fn(&[&str]) -> Result<(), String>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (index, device) in config.devices.iter().enumerate() {
+        let descriptor = DeviceDescriptor::from(device);
+        if descriptor.minimum_brightness_in_lumen > descriptor.maximum_brightness_in_lumen {
+            problems.push(format!(
+                "devices[{}]: `minimum_brightness_in_lumen` is greater than `maximum_brightness_in_lumen`",
+                index
+            ));
+        }
+        if descriptor.minimum_temperature_in_kelvin > descriptor.maximum_temperature_in_kelvin {
+            problems.push(format!(
+                "devices[{}]: `minimum_temperature_in_kelvin` is greater than `maximum_temperature_in_kelvin`",
+                index
+            ));
+        }
+        if let Some(other_index) = config.devices[..index]
+            .iter()
+            .position(|other| other.product_id == device.product_id)
+        {
+            problems.push(format!(
+                "devices[{}]: product ID 0x{:04x} is already configured by devices[{}], so this entry overrides it",
+                index, device.product_id, other_index
+            ));
+        }
+    }
+
+    let mut aliases: Vec<_> = config.aliases.iter().collect();
+    aliases.sort();
+    for (name, expansion) in aliases {
+        if is_built_in_command(name) {
+            problems.push(format!(
+                "aliases.{}: this is a built-in command, so the alias will never be used",
+                name
+            ));
+        }
+        let args = match shell_words::split(expansion) {
+            Ok(args) => args,
+            Err(error) => {
+                problems.push(format!("aliases.{}: {}", name, error));
+                continue;
+            }
+        };
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.first() {
+            None => problems.push(format!("aliases.{}: the alias is empty", name)),
+            // Aliases to other commands may be plugins, which can't be checked
+            Some(command) if is_built_in_command(command) => {
+                if let Err(error) = check_command(&args) {
+                    problems.push(format!("aliases.{}: {}", name, error));
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    problems
+}
+
+/// Returns a JSON Schema describing the configuration file, for editor autocompletion.
+pub fn config_schema() -> serde_json::Value {
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "litra configuration",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "devices": {
+                "description": "Additional devices to support, on top of those built into the library.",
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "additionalProperties": false,
+                    "required": ["product_id", "feature_index"],
+                    "properties": {
+                        "product_id": {
+                            "description": "The USB product ID of the device.",
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 65535
+                        },
+                        "model": {
+                            "description": "The model which the device is treated as.",
+                            "enum": ["glow", "beam", "beam_lx"],
+                            "default": "beam"
+                        },
+                        "feature_index": {
+                            "description": "The index of the HID++ illumination feature.",
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 255
+                        },
+                        "minimum_brightness_in_lumen": {
+                            "description": "Defaults to the minimum brightness of the model.",
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 65535
+                        },
+                        "maximum_brightness_in_lumen": {
+                            "description": "Defaults to the maximum brightness of the model.",
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 65535
+                        },
+                        "minimum_temperature_in_kelvin": {
+                            "description": "Defaults to the minimum temperature of the model.",
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 65535
+                        },
+                        "maximum_temperature_in_kelvin": {
+                            "description": "Defaults to the maximum temperature of the model.",
+                            "type": "integer",
+                            "minimum": 0,
+                            "maximum": 65535
+                        }
+                    }
+                }
+            },
+            "aliases": {
+                "description": "Shorthands for commands, e.g. `meeting = \"brightness --percentage 75\"`.",
+                "type": "object",
+                "additionalProperties": {
+                    "type": "string"
+                }
+            }
+        }
+    })
+}
+
+/// Expands a command alias from the configuration file, if the first argument after the program
+/// name is an alias rather than a built-in command. The alias is split into arguments like a
+/// shell would, so arguments containing spaces can be quoted.
+pub fn expand_alias(
+    args: Vec<OsString>,
+    is_built_in_command: impl Fn(&str) -> bool,
+) -> Result<Vec<OsString>, CliError> {
+    let Some(name) = args.get(1).and_then(|arg| arg.to_str()) else {
+        return Ok(args);
+    };
+    if name.starts_with('-') || is_built_in_command(name) {
+        return Ok(args);
+    }
+
+    let config = load_config()?;
+    let Some(expansion) = config.aliases.get(name) else {
+        return Ok(args);
+    };
+
+    expand(&args, expansion).map_err(|error| {
+        CliError::InvalidConfig(
+            config_path().map_or_else(String::new, |path| path.display().to_string()),
+            format!("aliases.{}: {}", name, error),
+        )
+    })
+}
+
+/// Replaces the alias in `args` with the arguments it expands to.
+fn expand(args: &[OsString], expansion: &str) -> Result<Vec<OsString>, shell_words::ParseError> {
+    Ok(args
+        .iter()
+        .take(1)
+        .cloned()
+        .chain(
+            shell_words::split(expansion)?
+                .into_iter()
+                .map(OsString::from),
+        )
+        .chain(args.iter().skip(2).cloned())
+        .collect())
+}
+
+/// Registers the devices from the configuration file, failing if any of them is invalid rather than
+/// registering ranges which commands can't use.
+fn register_devices(context: &mut Litra, config: &Config) -> Result<(), CliError> {
+    for (index, device) in config.devices.iter().enumerate() {
+        context
+            .register_device_descriptor(device.into())
+            .map_err(|error| {
+                CliError::InvalidConfig(
+                    config_path().map_or_else(String::new, |path| path.display().to_string()),
+                    format!("devices[{}]: {}", index, error),
+                )
+            })?;
+    }
+    Ok(())
+}
+
+/// Creates a [`Litra`] context with any devices from the configuration file registered and the
+/// global options applied.
+pub fn new_context(options: &GlobalOptions) -> Result<Litra, CliError> {
+    let config = load_config()?;
+    let mut context = Litra::new()?;
+    context.set_timeout(options.timeout);
+    context.set_retry_policy(RetryPolicy {
+        retries: options.retries,
+        ..RetryPolicy::default()
+    });
+    register_devices(&mut context, &config)?;
+    for device in &options.simulated_devices {
+        context.add_simulated_device(device.clone());
+    }
+    Ok(context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::collections::BTreeSet;
+
+    /// The example configuration from the README, made up of all of its TOML code blocks.
+    fn readme_config() -> String {
+        include_str!("../../README.md")
+            .split("```toml\n")
+            .skip(1)
+            .filter_map(|block| block.split("```").next())
+            .collect()
+    }
+
+    fn keys(value: &Value) -> BTreeSet<String> {
+        value.as_object().unwrap().keys().cloned().collect()
+    }
+
+    #[test]
+    fn schema_is_valid() {
+        assert!(jsonschema::meta::is_valid(&config_schema()));
+    }
+
+    #[test]
+    fn readme_config_matches_the_schema() {
+        let contents = readme_config();
+        let config: Config = toml::from_str(&contents).unwrap();
+        assert_eq!(config.devices.len(), 1);
+        assert!(config.aliases.contains_key("meeting"));
+
+        let config: Value = toml::from_str(&contents).unwrap();
+        let schema = config_schema();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(&config)
+            .map(|error| error.to_string())
+            .collect();
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        // The example uses every option, so the schema can't describe options which don't exist
+        assert_eq!(keys(&schema["properties"]), keys(&config));
+        assert_eq!(
+            keys(&schema["properties"]["devices"]["items"]["properties"]),
+            keys(&config["devices"][0])
+        );
+    }
+
+    #[test]
+    fn rejects_devices_with_inverted_ranges_on_load() {
+        let mut context = Litra::new().unwrap();
+        let valid: Config = toml::from_str(
+            "[[devices]]\nproduct_id = 0xc904\nfeature_index = 0x06\nminimum_brightness_in_lumen = 20\nmaximum_brightness_in_lumen = 250",
+        )
+        .unwrap();
+        assert!(register_devices(&mut context, &valid).is_ok());
+
+        for contents in [
+            "[[devices]]\nproduct_id = 0xc905\nfeature_index = 0x06\nminimum_brightness_in_lumen = 250\nmaximum_brightness_in_lumen = 20",
+            "[[devices]]\nproduct_id = 0xc905\nfeature_index = 0x06\nminimum_temperature_in_kelvin = 6500\nmaximum_temperature_in_kelvin = 2700",
+        ] {
+            let config: Config = toml::from_str(contents).unwrap();
+            let error = register_devices(&mut context, &config).unwrap_err();
+            assert!(
+                matches!(&error, CliError::InvalidConfig(_, message) if message.starts_with("devices[0]: ")),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn expands_aliases_with_quoted_arguments() {
+        let args: Vec<OsString> = ["litra", "short", "--serial-number", "2325FE7044P8"]
+            .into_iter()
+            .map(OsString::from)
+            .collect();
+        assert_eq!(
+            expand(&args, "status --short --template \"{emoji} {brightness}\"").unwrap(),
+            [
+                "litra",
+                "status",
+                "--short",
+                "--template",
+                "{emoji} {brightness}",
+                "--serial-number",
+                "2325FE7044P8"
+            ]
+        );
+        assert!(expand(&args, "status --template \"{emoji}").is_err());
+
+        let config: Config =
+            toml::from_str("[aliases]\nshort = 'status --template \"{emoji}'").unwrap();
+        assert_eq!(
+            validate_config(&config, |command| command == "status", |_| Ok(())),
+            ["aliases.short: missing closing quote"]
+        );
+    }
+
+    #[test]
+    fn schema_rejects_configs_which_fail_to_parse() {
+        let validator = jsonschema::validator_for(&config_schema()).unwrap();
+        for contents in [
+            "[[devices]]\nproduct_id = 0xc904\nfeature_index = 0x06\nmodel = \"lamp\"",
+            "[[devices]]\nproduct_id = 0xc904",
+            "[[devices]]\nproduct_id = 0xc904\nfeature_index = 0x06\ncolor = \"red\"",
+            "[aliases]\nmeeting = 75",
+            "[settings]",
+        ] {
+            assert!(toml::from_str::<Config>(contents).is_err(), "{}", contents);
+            let config: Value = toml::from_str(contents).unwrap();
+            assert!(!validator.is_valid(&config), "{}", contents);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Listens for DMX data sent over the network with Art-Net or sACN (E1.31), so that lighting
+//! consoles and software like QLC+ can control devices as ordinary fixtures.
+//!
+//! Each device uses two consecutive channels, starting from the configured address: intensity,
+//! where 0 turns the device off, and color temperature.
+
+use crate::{config, explain_open_error, CliError, CliResult};
+use litra::DeviceHandle;
+use std::net::{Ipv4Addr, UdpSocket};
+
+const ART_NET_PORT: u16 = 6454;
+const ART_NET_ID: &[u8] = b"Art-Net\0";
+const ART_NET_OP_DMX: u16 = 0x5000;
+
+const SACN_PORT: u16 = 5568;
+const SACN_ID: &[u8] = b"ASC-E1.17\0\0\0";
+const SACN_ROOT_VECTOR_DATA: u32 = 0x0000_0004;
+const SACN_FRAMING_VECTOR_DATA: u32 = 0x0000_0002;
+
+/// The number of DMX channels used by each device.
+const CHANNELS_PER_DEVICE: usize = 2;
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Protocol {
+    ArtNet,
+    Sacn,
+}
+
+struct Fixture {
+    serial_number: String,
+    device_handle: DeviceHandle,
+    /// The index of the fixture's first channel in the universe's data.
+    offset: usize,
+    last_values: Option<[u8; CHANNELS_PER_DEVICE]>,
+}
+
+impl Fixture {
+    fn apply(&mut self, data: &[u8]) -> CliResult {
+        let Some(values) = data
+            .get(self.offset..self.offset + CHANNELS_PER_DEVICE)
+            .and_then(|values| <[u8; CHANNELS_PER_DEVICE]>::try_from(values).ok())
+        else {
+            return Ok(());
+        };
+        if self.last_values == Some(values) {
+            return Ok(());
+        }
+
+        let [intensity, temperature] = values;
+        let device_handle = &self.device_handle;
+        if intensity == 0 {
+            device_handle.set_on(false)?;
+        } else {
+            device_handle.set_brightness_in_lumen(scale(
+                intensity - 1,
+                254,
+                device_handle.minimum_brightness_in_lumen(),
+                device_handle.maximum_brightness_in_lumen(),
+            ))?;
+            let step = device_handle.temperature_step_in_kelvin();
+            let temperature_in_kelvin = scale(
+                temperature,
+                255,
+                device_handle.minimum_temperature_in_kelvin() / step,
+                device_handle.maximum_temperature_in_kelvin() / step,
+            ) * step;
+            device_handle.set_temperature_in_kelvin(temperature_in_kelvin)?;
+            device_handle.set_on(true)?;
+        }
+
+        // Only remembered once applied, so values which failed are tried again with the next packet
+        self.last_values = Some(values);
+        Ok(())
+    }
+}
+
+/// Scales a DMX value from `0..=maximum_value` to the range.
+fn scale(value: u8, maximum_value: u8, minimum: u16, maximum: u16) -> u16 {
+    let range = u32::from(maximum - minimum);
+    minimum
+        + ((u32::from(value) * range + u32::from(maximum_value) / 2) / u32::from(maximum_value))
+            as u16
+}
+
+/// Returns the universe and DMX data from an ArtDmx packet.
+fn parse_art_net(packet: &[u8]) -> Option<(u16, &[u8])> {
+    if packet.get(..8)? != ART_NET_ID
+        || u16::from_le_bytes([*packet.get(8)?, *packet.get(9)?]) != ART_NET_OP_DMX
+    {
+        return None;
+    }
+    // The universe is 15 bits, split into the sub-net and universe (low byte) and the net
+    let universe = u16::from_le_bytes([*packet.get(14)?, *packet.get(15)?]) & 0x7fff;
+    let length = usize::from(u16::from_be_bytes([*packet.get(16)?, *packet.get(17)?]));
+    Some((universe, packet.get(18..18 + length)?))
+}
+
+/// Returns the universe and DMX data from an E1.31 data packet.
+fn parse_sacn(packet: &[u8]) -> Option<(u16, &[u8])> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(
+            packet.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+    if packet.get(4..16)? != SACN_ID
+        || read_u32(18)? != SACN_ROOT_VECTOR_DATA
+        || read_u32(40)? != SACN_FRAMING_VECTOR_DATA
+    {
+        return None;
+    }
+    let universe = u16::from_be_bytes([*packet.get(113)?, *packet.get(114)?]);
+    // The property values start with the DMX start code, which is 0 for dimmer data
+    let count = usize::from(u16::from_be_bytes([*packet.get(123)?, *packet.get(124)?]));
+    if count == 0 || *packet.get(125)? != 0 {
+        return None;
+    }
+    Some((universe, packet.get(126..125 + count)?))
+}
+
+pub fn handle_dmx_command(
+    options: &config::GlobalOptions,
+    protocol: Protocol,
+    universe: Option<u16>,
+    address: u16,
+    serial_numbers: &[String],
+) -> CliResult {
+    if !(1..=512).contains(&address) {
+        return Err(CliError::DmxFailed(
+            "The address must be between 1 and 512".to_string(),
+        ));
+    }
+    let universe = universe.unwrap_or(match protocol {
+        Protocol::ArtNet => 0,
+        Protocol::Sacn => 1,
+    });
+
+    let context = config::new_context(options)?;
+    let mut devices: Vec<_> = context
+        .get_connected_devices()
+        .filter_map(|device| {
+            let serial_number = device.serial_number()?.to_string();
+            Some((serial_number, device))
+        })
+        .filter(|(serial_number, _)| {
+            serial_numbers.is_empty() || serial_numbers.contains(serial_number)
+        })
+        .collect();
+    // Devices are assigned channels in the order they were given, or by serial number
+    if serial_numbers.is_empty() {
+        devices.sort_by(|(a, _), (b, _)| a.cmp(b));
+    } else {
+        devices.sort_by_key(|(serial_number, _)| {
+            serial_numbers.iter().position(|s| s == serial_number)
+        });
+    }
+    if devices.is_empty() {
+        return Err(CliError::DeviceNotFound);
+    }
+
+    let mut fixtures = Vec::new();
+    for (index, (serial_number, device)) in devices.into_iter().enumerate() {
+        let offset = usize::from(address - 1) + index * CHANNELS_PER_DEVICE;
+        if offset + CHANNELS_PER_DEVICE > 512 {
+            return Err(CliError::DmxFailed(format!(
+                "There aren't enough channels left in the universe for {}",
+                serial_number
+            )));
+        }
+        fixtures.push(Fixture {
+            serial_number,
+            device_handle: device.open(&context).map_err(explain_open_error)?,
+            offset,
+            last_values: None,
+        });
+    }
+
+    let socket = match protocol {
+        Protocol::ArtNet => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, ART_NET_PORT)),
+        Protocol::Sacn => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, SACN_PORT)).and_then(|socket| {
+            // sACN data is sent to a multicast group for each universe
+            let [high, low] = universe.to_be_bytes();
+            socket
+                .join_multicast_v4(&Ipv4Addr::new(239, 255, high, low), &Ipv4Addr::UNSPECIFIED)?;
+            Ok(socket)
+        }),
+    }
+    .map_err(|error| CliError::DmxFailed(error.to_string()))?;
+
+    println!("Listening for DMX on universe {}:", universe);
+    for fixture in &fixtures {
+        println!(
+            "- {} ({}): channels {}-{}",
+            fixture.device_handle.device_type(),
+            fixture.serial_number,
+            fixture.offset + 1,
+            fixture.offset + CHANNELS_PER_DEVICE
+        );
+    }
+
+    let mut buffer = [0u8; 1024];
+    loop {
+        let length = socket
+            .recv(&mut buffer)
+            .map_err(|error| CliError::DmxFailed(error.to_string()))?;
+        let packet = &buffer[..length];
+        let parsed = match protocol {
+            Protocol::ArtNet => parse_art_net(packet),
+            Protocol::Sacn => parse_sacn(packet),
+        };
+        let Some((packet_universe, data)) = parsed else {
+            continue;
+        };
+        if packet_universe != universe {
+            continue;
+        }
+
+        for fixture in &mut fixtures {
+            if let Err(error) = fixture.apply(data) {
+                if options.strict {
+                    return Err(error);
+                }
+                eprintln!("Failed to update {}: {}", fixture.serial_number, error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An ArtDmx packet for universe 0x0123 (net 1, sub-net 2, universe 3) with four channels.
+    const ART_NET_PACKET: [u8; 22] = [
+        b'A', b'r', b't', b'-', b'N', b'e', b't', 0x00, // ID
+        0x00, 0x50, // OpDmx, little-endian
+        0x00, 0x0e, // Protocol version 14
+        0x07, // Sequence
+        0x00, // Physical port
+        0x23, // Sub-net and universe
+        0x01, // Net
+        0x00, 0x04, // Length, big-endian
+        0x80, 0xff, 0x00, 0x10, // DMX data
+    ];
+
+    /// Builds an E1.31 data packet for the universe, with the start code followed by the data.
+    fn sacn_packet(universe: u16, start_code: u8, data: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x00; 126];
+        packet[0..2].copy_from_slice(&[0x00, 0x10]); // Preamble size
+        packet[4..16].copy_from_slice(SACN_ID);
+        packet[18..22].copy_from_slice(&SACN_ROOT_VECTOR_DATA.to_be_bytes());
+        packet[40..44].copy_from_slice(&SACN_FRAMING_VECTOR_DATA.to_be_bytes());
+        packet[44..49].copy_from_slice(b"Test\0"); // Source name
+        packet[108] = 100; // Priority
+        packet[113..115].copy_from_slice(&universe.to_be_bytes());
+        packet[117] = 0x02; // DMP set property
+        packet[118] = 0xa1; // Address and data type
+        packet[121..123].copy_from_slice(&[0x00, 0x01]); // Address increment
+        let count = u16::try_from(data.len() + 1).unwrap();
+        packet[123..125].copy_from_slice(&count.to_be_bytes());
+        packet[125] = start_code;
+        packet.extend_from_slice(data);
+        packet
+    }
+
+    #[test]
+    fn parses_art_net_dmx_packets() {
+        assert_eq!(
+            parse_art_net(&ART_NET_PACKET),
+            Some((0x0123, &[0x80, 0xff, 0x00, 0x10][..]))
+        );
+    }
+
+    #[test]
+    fn ignores_art_net_packets_which_are_not_dmx_data() {
+        let mut poll_packet = ART_NET_PACKET;
+        poll_packet[8..10].copy_from_slice(&[0x00, 0x20]); // OpPoll
+        assert_eq!(parse_art_net(&poll_packet), None);
+
+        let mut other_packet = ART_NET_PACKET;
+        other_packet[0] = b'B';
+        assert_eq!(parse_art_net(&other_packet), None);
+    }
+
+    #[test]
+    fn ignores_truncated_art_net_packets() {
+        assert_eq!(parse_art_net(&ART_NET_PACKET[..20]), None);
+        assert_eq!(parse_art_net(&ART_NET_PACKET[..12]), None);
+    }
+
+    #[test]
+    fn parses_sacn_data_packets() {
+        let packet = sacn_packet(7, 0x00, &[0x01, 0x02, 0x03]);
+        assert_eq!(parse_sacn(&packet), Some((7, &[0x01, 0x02, 0x03][..])));
+    }
+
+    #[test]
+    fn ignores_sacn_packets_with_another_start_code() {
+        let packet = sacn_packet(7, 0xdd, &[0x01, 0x02, 0x03]);
+        assert_eq!(parse_sacn(&packet), None);
+    }
+
+    #[test]
+    fn ignores_sacn_packets_which_are_not_data() {
+        let mut packet = sacn_packet(7, 0x00, &[0x01]);
+        // An extended (e.g. discovery) packet uses a different root vector
+        packet[18..22].copy_from_slice(&0x0000_0008u32.to_be_bytes());
+        assert_eq!(parse_sacn(&packet), None);
+    }
+
+    #[test]
+    fn ignores_truncated_sacn_packets() {
+        let packet = sacn_packet(7, 0x00, &[0x01, 0x02, 0x03]);
+        assert_eq!(parse_sacn(&packet[..127]), None);
+        assert_eq!(parse_sacn(&packet[..60]), None);
+    }
+
+    #[test]
+    fn scales_values_to_the_range() {
+        assert_eq!(scale(0, 254, 20, 250), 20);
+        assert_eq!(scale(254, 254, 20, 250), 250);
+        assert_eq!(scale(127, 254, 20, 250), 135);
+        assert_eq!(scale(255, 255, 27, 65), 65);
+        assert_eq!(scale(0, 255, 27, 65), 27);
+    }
+}
@@ -0,0 +1,148 @@
+//! Simulates devices with the `litra` crate's mock transport, so that commands and integrations
+//! can be developed and tested on machines without a Logitech Litra device attached.
+
+use crate::{config, is_built_in_command, run_command, Cli, CliError, CliResult, Commands};
+use clap::Parser;
+use litra::mock::MockDevice;
+use std::ffi::OsString;
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// The prompt shown before each command when reading commands from a terminal.
+const PROMPT: &str = "litra> ";
+
+/// Returns the serial number of the simulated device with the given index, starting from 1.
+fn serial_number(index: u8) -> String {
+    format!("EMULATED{}", index)
+}
+
+/// Parses a command to run against the simulated devices, expanding aliases like the top-level
+/// command does.
+fn parse_command(args: impl IntoIterator<Item = OsString>) -> Result<Cli, clap::Error> {
+    let args = std::iter::once(OsString::from("litra"))
+        .chain(args)
+        .collect::<Vec<_>>();
+    let args = config::expand_alias(args.clone(), is_built_in_command).unwrap_or(args);
+    Cli::try_parse_from(args)
+}
+
+/// Runs a parsed command against the simulated devices. Global options like `--timeout` apply if
+/// they are given with the command, rather than to `litra emulate`.
+fn run_simulated_command(cli: &Cli, simulated_devices: &[MockDevice]) -> CliResult {
+    if matches!(cli.command, Commands::Emulate { .. }) {
+        return Err(CliError::EmulateFailed(
+            "`litra emulate` can't be run against simulated devices".to_string(),
+        ));
+    }
+    let options = config::GlobalOptions {
+        timeout: cli.timeout,
+        retries: cli.retries,
+        strict: cli.strict,
+        simulated_devices: simulated_devices.to_vec(),
+    };
+    run_command(&cli.command, &options)
+}
+
+/// Runs `command` against `count` simulated devices of the given model, or each command read
+/// from stdin if there isn't one. The simulated devices keep their state between commands, and are
+/// listed after any real devices.
+pub fn handle_emulate_command(model: config::Model, count: u8, command: &[OsString]) -> CliResult {
+    let simulated_devices = (1..=count)
+        .map(|index| MockDevice::new(model.into(), serial_number(index)))
+        .collect::<Vec<_>>();
+
+    if !command.is_empty() {
+        let cli = parse_command(command.iter().cloned()).unwrap_or_else(|error| error.exit());
+        return run_simulated_command(&cli, &simulated_devices);
+    }
+
+    let stdin = io::stdin();
+    let is_terminal = stdin.is_terminal();
+    if is_terminal {
+        let serial_numbers = simulated_devices
+            .iter()
+            .map(MockDevice::serial_number)
+            .collect::<Vec<_>>();
+        eprintln!(
+            "Simulating {} {} device(s): {}. Enter commands like `devices`, or press Ctrl+D to exit.",
+            count,
+            litra::DeviceType::from(model),
+            serial_numbers.join(", ")
+        );
+    }
+
+    let mut line = String::new();
+    loop {
+        if is_terminal {
+            print!("{}", PROMPT);
+            io::stdout().flush().map_err(CliError::StdioFailed)?;
+        }
+        line.clear();
+        if stdin
+            .lock()
+            .read_line(&mut line)
+            .map_err(CliError::StdioFailed)?
+            == 0
+        {
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_command(line.split_whitespace().map(OsString::from)) {
+            Ok(cli) => {
+                if let Err(error) = run_simulated_command(&cli, &simulated_devices) {
+                    eprintln!("{}", error);
+                }
+            }
+            // Also prints `--help` and `--version`, which are "errors" to clap
+            Err(error) => error.print().map_err(CliError::StdioFailed)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litra::DeviceType;
+    use std::time::Duration;
+
+    fn args(command: &str) -> impl Iterator<Item = OsString> + '_ {
+        command.split_whitespace().map(OsString::from)
+    }
+
+    #[test]
+    fn parses_commands_with_global_options() {
+        let cli = parse_command(args("--timeout 2s on --serial-number EMULATED1")).unwrap();
+        assert_eq!(cli.timeout, Duration::from_secs(2));
+        assert!(matches!(cli.command, Commands::On { .. }));
+    }
+
+    #[test]
+    fn runs_commands_against_simulated_devices() {
+        let device = MockDevice::new(DeviceType::LitraBeamLX, serial_number(1));
+        let simulated_devices = [device.clone()];
+
+        for command in [
+            "on --serial-number EMULATED1",
+            "brightness --serial-number EMULATED1 --value 200",
+            "temperature-up --serial-number EMULATED1 --value 300",
+        ] {
+            let cli = parse_command(args(command)).unwrap();
+            run_simulated_command(&cli, &simulated_devices).unwrap();
+        }
+
+        assert!(device.is_on());
+        assert_eq!(device.brightness_in_lumen(), 200);
+        assert_eq!(device.temperature_in_kelvin(), 3000);
+    }
+
+    #[test]
+    fn doesnt_emulate_inside_emulate() {
+        let cli = parse_command(args("emulate -- on")).unwrap();
+        assert!(matches!(
+            run_simulated_command(&cli, &[]),
+            Err(CliError::EmulateFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,1921 @@
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, terminal};
+use litra::{Device, DeviceError, DeviceHandle, DeviceResult, Litra};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::fmt;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::num::TryFromIntError;
+use std::path::PathBuf;
+use std::process::{Command, ExitCode};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+mod bench;
+mod config;
+mod dmx;
+mod emulate;
+mod obs;
+mod osc;
+mod pin;
+mod probe;
+mod rpc;
+mod schema;
+mod self_test;
+mod snapshot;
+mod stress;
+mod sync;
+mod white_point;
+
+/// Control your USB-connected Logitech Litra lights from the command line
+#[derive(Debug, Parser)]
+#[clap(name = "litra", version)]
+struct Cli {
+    // Test
+    #[clap(subcommand)]
+    command: Commands,
+    #[clap(
+        long,
+        global = true,
+        default_value = "1s",
+        value_parser = humantime::parse_duration,
+        help = "How long to wait for a device to respond before giving up, e.g. `500ms` or `2s`"
+    )]
+    timeout: Duration,
+    #[clap(
+        long,
+        global = true,
+        default_value_t = 0,
+        help = "The number of times to retry an operation which fails, waiting 100ms before the first retry and doubling the wait each time, up to 1 second"
+    )]
+    retries: u32,
+    #[clap(
+        long,
+        global = true,
+        action,
+        help = "When changing several devices, stop at the first device which fails rather than carrying on with the others"
+    )]
+    strict: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum Commands {
+    /// Turn your Logitech Litra device on
+    On {
+        #[clap(flatten)]
+        target: DeviceTarget,
+    },
+    /// Turn your Logitech Litra device off
+    Off {
+        #[clap(flatten)]
+        target: DeviceTarget,
+    },
+    /// Toggles your Logitech Litra device on or off
+    Toggle {
+        #[clap(flatten)]
+        target: DeviceTarget,
+    },
+    /// Exits successfully if your Logitech Litra device is on, or with exit code 1 if it's off, printing nothing. If the device can't be found or read, it exits with code 2 and prints the error. This is intended for shell conditionals and status bar scripts.
+    IsOn {
+        #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+        serial_number: Option<String>,
+        #[clap(
+            long,
+            action,
+            conflicts_with_all = ["serial_number", "all"],
+            help = "Succeed if any connected Logitech Litra device is on"
+        )]
+        any: bool,
+        #[clap(
+            long,
+            short,
+            action,
+            conflicts_with = "serial_number",
+            help = "Succeed only if all connected Logitech Litra devices are on"
+        )]
+        all: bool,
+    },
+    /// Shows the state of your Logitech Litra device
+    Status {
+        #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+        serial_number: Option<String>,
+        #[clap(
+            long,
+            action,
+            help = "Print a single line using a template, for prompts and status bars"
+        )]
+        short: bool,
+        #[clap(
+            long,
+            requires = "short",
+            default_value = DEFAULT_STATUS_TEMPLATE,
+            help = "The template for `--short`. `{emoji}`, `{power}`, `{brightness}`, `{brightness_percentage}`, `{temperature}`, `{device_type}` and `{serial_number}` are replaced with the device's state."
+        )]
+        template: String,
+    },
+    /// Waits until your Logitech Litra device reaches a state, e.g. after another app or a person has adjusted it
+    #[clap(group = ArgGroup::new("condition").required(true).multiple(true))]
+    WaitUntil {
+        #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+        serial_number: Option<String>,
+        #[clap(
+            long,
+            action,
+            conflicts_with = "off",
+            group = "condition",
+            help = "Wait until the device is on"
+        )]
+        on: bool,
+        #[clap(
+            long,
+            action,
+            group = "condition",
+            help = "Wait until the device is off"
+        )]
+        off: bool,
+        #[clap(
+            long,
+            group = "condition",
+            help = "Wait until the device's brightness is at least this many lumens"
+        )]
+        brightness_at_least: Option<u16>,
+        #[clap(
+            long,
+            value_parser = humantime::parse_duration,
+            help = "How long to wait before failing, e.g. `60s`. By default, this waits forever."
+        )]
+        max_wait: Option<Duration>,
+        #[clap(
+            long,
+            default_value_t = 250,
+            help = "How often to check the device's state, in milliseconds"
+        )]
+        poll_interval_ms: u64,
+    },
+    /// Sets the brightness of your Logitech Litra device
+    #[clap(group = ArgGroup::new("brightness").required(true).multiple(false))]
+    Brightness {
+        #[clap(flatten)]
+        target: DeviceTarget,
+        #[clap(
+            long,
+            short,
+            help = "The brightness to set, measured in lumens. This can be set to any value between the minimum and maximum for the device returned by the `devices` command.",
+            group = "brightness"
+        )]
+        value: Option<u16>,
+        #[clap(
+            long,
+            short,
+            help = "The brightness to set, as a percentage of the maximum brightness",
+            group = "brightness"
+        )]
+        percentage: Option<u8>,
+    },
+    /// Increases the brightness of your Logitech Litra device. The command will error if trying to increase the brightness beyond the device's maximum.
+    #[clap(group = ArgGroup::new("brightness-up").required(true).multiple(false))]
+    BrightnessUp {
+        #[clap(flatten)]
+        target: DeviceTarget,
+        #[clap(
+            long,
+            short,
+            help = "The amount to increase the brightness by, measured in lumens.",
+            group = "brightness-up"
+        )]
+        value: Option<u16>,
+        #[clap(
+            long,
+            short,
+            help = "The number of percentage points to increase the brightness by",
+            group = "brightness-up"
+        )]
+        percentage: Option<u8>,
+    },
+    /// Decreases the brightness of your Logitech Litra device. The command will error if trying to decrease the brightness below the device's minimum.
+    #[clap(group = ArgGroup::new("brightness-down").required(true).multiple(false))]
+    BrightnessDown {
+        #[clap(flatten)]
+        target: DeviceTarget,
+        #[clap(
+            long,
+            short,
+            help = "The amount to decrease the brightness by, measured in lumens.",
+            group = "brightness-down"
+        )]
+        value: Option<u16>,
+        #[clap(
+            long,
+            short,
+            help = "The number of percentage points to reduce the brightness by",
+            group = "brightness-down"
+        )]
+        percentage: Option<u8>,
+    },
+    /// Sets the temperature of your Logitech Litra device
+    Temperature {
+        #[clap(flatten)]
+        target: DeviceTarget,
+        #[clap(
+            long,
+            short,
+            help = "The temperature to set, measured in Kelvin. This can be set to any multiple of 100 between the minimum and maximum for the device returned by the `devices` command."
+        )]
+        value: u16,
+    },
+    /// Increases the temperature of your Logitech Litra device. The command will error if trying to increase the temperature beyond the device's maximum.
+    TemperatureUp {
+        #[clap(flatten)]
+        target: DeviceTarget,
+        #[clap(
+            long,
+            short,
+            help = "The amount to increase the temperature by, measured in Kelvin. This must be a multiple of 100."
+        )]
+        value: u16,
+    },
+    /// Decreases the temperature of your Logitech Litra device. The command will error if trying to decrease the temperature below the device's minimum.
+    TemperatureDown {
+        #[clap(flatten)]
+        target: DeviceTarget,
+        #[clap(
+            long,
+            short,
+            help = "The amount to decrease the temperature by, measured in Kelvin. This must be a multiple of 100."
+        )]
+        value: u16,
+    },
+    /// Sets the temperature of your Logitech Litra device to match a white point, e.g. to keep it consistent with a calibrated monitor. The closest temperature the device supports is used.
+    #[clap(group = ArgGroup::new("white-point-source").required(true).multiple(false))]
+    Match {
+        #[clap(flatten)]
+        target: DeviceTarget,
+        #[clap(
+            long,
+            value_parser = white_point::parse_white_point,
+            help = "The white point to match, either a standard illuminant (D50, D55, D65 or D75) or a temperature in Kelvin",
+            group = "white-point-source"
+        )]
+        white_point: Option<f64>,
+        #[clap(
+            long,
+            help = "The path of a display's ICC profile, to match its white point",
+            group = "white-point-source"
+        )]
+        icc_profile: Option<PathBuf>,
+        #[clap(
+            long,
+            action,
+            help = "Match the white point of your display's ICC profile, found using colord. Only supported on Linux.",
+            group = "white-point-source"
+        )]
+        display: bool,
+    },
+    /// List Logitech Litra devices connected to your computer
+    Devices {
+        #[clap(long, short, action, help = "Return the results in JSON format")]
+        json: bool,
+        #[clap(
+            long,
+            action,
+            help = "Also list Logitech devices which look like lights but aren't supported. With `--json`, the results are returned as an object with `devices` and `unsupported` keys."
+        )]
+        all: bool,
+        #[clap(
+            long,
+            conflicts_with = "all",
+            value_parser = humantime::parse_duration,
+            help = "Keep reading and printing the state of connected devices at this interval, e.g. `2s`, until interrupted. With `--json`, each device's state is printed as a separate line of JSON with a `timestamp`."
+        )]
+        poll: Option<Duration>,
+    },
+    /// Run a long-lived JSON-RPC server over standard input and output, for Stream Deck plugins and other integrations
+    Rpc {
+        #[clap(
+            long,
+            default_value_t = 500,
+            help = "How often to check connected devices for changes when subscribed, in milliseconds"
+        )]
+        poll_interval_ms: u64,
+    },
+    /// Turn your Logitech Litra device on while OBS Studio is streaming, recording or running its virtual camera, and restore its previous state afterwards. This requires the obs-websocket server, included in OBS 28 and later, to be enabled.
+    Obs {
+        #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+        serial_number: Option<String>,
+        #[clap(
+            long,
+            default_value = "ws://localhost:4455",
+            help = "The URL of the obs-websocket server"
+        )]
+        url: String,
+        #[clap(
+            long,
+            env = "LITRA_OBS_PASSWORD",
+            hide_env_values = true,
+            help = "The password for the obs-websocket server, if enabled. Use the environment variable to keep it out of your shell history and process list."
+        )]
+        password: Option<String>,
+        #[clap(long, help = "The brightness to set while live, measured in lumens")]
+        brightness: Option<u16>,
+        #[clap(long, help = "The temperature to set while live, measured in Kelvin")]
+        temperature: Option<u16>,
+    },
+    /// Measure how long it takes to enumerate, open, read from and write to your Logitech Litra devices
+    Bench {
+        #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+        serial_number: Option<String>,
+        #[clap(
+            long,
+            short,
+            default_value_t = 20,
+            help = "The number of times to repeat each operation"
+        )]
+        iterations: u32,
+    },
+    /// Cycle your Logitech Litra device through its power states, brightness range and every valid temperature, checking that each setting is applied. Its original state is restored afterwards.
+    SelfTest {
+        #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+        serial_number: Option<String>,
+    },
+    /// Repeatedly send random operations to your Logitech Litra device for a long period, recording error rates and timings. Its original state is restored afterwards.
+    Stress {
+        #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+        serial_number: Option<String>,
+        #[clap(
+            long,
+            default_value = "10m",
+            value_parser = humantime::parse_duration,
+            help = "How long to run for, e.g. `30s`, `10m` or `1h`"
+        )]
+        duration: Duration,
+        #[clap(
+            long,
+            default_value_t = 5,
+            help = "The number of operations to send each second"
+        )]
+        ops_per_second: u32,
+    },
+    /// Send the HID++ queries used for supported devices to a Logitech device which isn't recognized yet, and report which respond. This helps add support for new models.
+    Probe {
+        #[clap(
+            long,
+            help = "The HID path of the device to probe, as listed by your operating system"
+        )]
+        device_path: String,
+        #[clap(long, short, action, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+    /// Keep Logitech Litra devices matched by mirroring the power state, brightness and temperature of one device to the others, including changes made with its buttons
+    Sync {
+        #[clap(long, help = "The serial number of the device to copy changes from")]
+        master: String,
+        #[clap(
+            long,
+            short,
+            help = "The serial number of a device to copy changes to. This can be repeated. Defaults to all other connected devices."
+        )]
+        serial_number: Vec<String>,
+        #[clap(
+            long,
+            default_value_t = 250,
+            help = "How often to check the master device for changes, in milliseconds"
+        )]
+        poll_interval_ms: u64,
+    },
+    /// Keeps your Logitech Litra device on at a brightness and temperature, putting it back whenever it's changed with its buttons or by other software
+    Pin {
+        #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+        serial_number: Option<String>,
+        #[clap(
+            long,
+            value_parser = pin::parse_brightness,
+            help = "The brightness to keep, either in lumens (e.g. `250`) or as a percentage of the device's range (e.g. `80%`)"
+        )]
+        brightness: Option<pin::Brightness>,
+        #[clap(long, help = "The temperature to keep, in Kelvin")]
+        temperature: Option<u16>,
+        #[clap(
+            long,
+            value_parser = humantime::parse_duration,
+            help = "How long to keep the device pinned, e.g. `2h`. By default, it stays pinned until interrupted."
+        )]
+        duration: Option<Duration>,
+        #[clap(
+            long,
+            default_value_t = 250,
+            help = "How often to check the device's state, in milliseconds"
+        )]
+        poll_interval_ms: u64,
+    },
+    /// Control your Logitech Litra devices as DMX fixtures from a lighting console or software like QLC+, using Art-Net or sACN. Each device uses two channels: intensity, where 0 turns it off, and color temperature.
+    Dmx {
+        #[clap(
+            long,
+            value_enum,
+            default_value = "art-net",
+            help = "The protocol to listen for"
+        )]
+        protocol: dmx::Protocol,
+        #[clap(
+            long,
+            help = "The DMX universe to listen to. Defaults to 0 for Art-Net and 1 for sACN."
+        )]
+        universe: Option<u16>,
+        #[clap(
+            long,
+            default_value_t = 1,
+            help = "The DMX address of the first device's first channel. Other devices use the channels after it."
+        )]
+        address: u16,
+        #[clap(
+            long,
+            short,
+            help = "The serial number of a device to control. This can be repeated, and channels are assigned in the order given. Defaults to all connected devices, ordered by serial number."
+        )]
+        serial_number: Vec<String>,
+    },
+    /// Run an Open Sound Control (OSC) server, so AV tools, TouchOSC layouts and show control software can control your Logitech Litra devices. Send messages to `/litra/<serial number>/power`, `/litra/<serial number>/brightness` or `/litra/<serial number>/temperature`, using `*` as the serial number to control all devices.
+    Osc {
+        #[clap(long, default_value_t = 8000, help = "The UDP port to listen on")]
+        port: u16,
+    },
+    /// Check or describe the configuration file
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Print a JSON Schema for a machine-readable output, like `litra devices --json` or `litra rpc` messages
+    Schema {
+        #[clap(value_enum, help = "The output to describe")]
+        output: schema::Output,
+    },
+    /// Save or restore the state of all connected Logitech Litra devices
+    Snapshot {
+        #[clap(subcommand)]
+        command: SnapshotCommands,
+    },
+    /// Simulate Logitech Litra devices, so that commands and integrations can be tried out without any hardware. Runs the command given after `--` against the simulated devices, or reads commands from stdin, one per line.
+    Emulate {
+        #[clap(
+            long = "type",
+            value_enum,
+            default_value = "beam",
+            help = "The model of the simulated devices"
+        )]
+        device_type: config::Model,
+        #[clap(
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u8).range(1..),
+            help = "The number of devices to simulate"
+        )]
+        count: u8,
+        #[clap(
+            last = true,
+            help = "The command to run, e.g. `-- brightness --value 100`"
+        )]
+        command: Vec<OsString>,
+    },
+    /// Manage the udev rules which allow non-root users to access Logitech Litra devices
+    #[cfg(target_os = "linux")]
+    Udev {
+        #[clap(subcommand)]
+        command: UdevCommands,
+    },
+}
+
+/// The device or devices a command changes.
+#[derive(Debug, Args)]
+struct DeviceTarget {
+    #[clap(long, short, help = "The serial number of the Logitech Litra device")]
+    serial_number: Option<String>,
+    #[clap(
+        long,
+        short,
+        action,
+        conflicts_with = "serial_number",
+        help = "Apply to all connected Logitech Litra devices, rather than just the first one"
+    )]
+    all: bool,
+    #[clap(
+        long,
+        short,
+        action,
+        requires = "all",
+        help = "Don't ask for confirmation before changing more than one device"
+    )]
+    yes: bool,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommands {
+    /// Check the configuration file for problems, like invalid values or aliases to commands with invalid arguments
+    Validate,
+    /// Print a JSON Schema for the configuration file, which editors can use for autocompletion
+    Schema,
+}
+
+#[derive(Debug, Subcommand)]
+enum SnapshotCommands {
+    /// Save the power state, brightness and temperature of all connected devices under a name
+    Save {
+        #[clap(help = "The name of the snapshot")]
+        name: String,
+    },
+    /// Restore a saved snapshot to the connected devices, matching them by serial number
+    Restore {
+        #[clap(help = "The name of the snapshot")]
+        name: String,
+    },
+    /// List the saved snapshots
+    List,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Subcommand)]
+enum UdevCommands {
+    /// Install udev rules for all supported devices, prompting for administrator privileges with `sudo` if needed
+    Install {
+        #[clap(
+            long,
+            action,
+            help = "Print the rules to standard output instead of installing them"
+        )]
+        print: bool,
+    },
+}
+
+fn percentage_within_range(percentage: u32, start_range: u32, end_range: u32) -> u32 {
+    let range = end_range as f64 - start_range as f64;
+    let result = (percentage as f64 / 100.0) * range + start_range as f64;
+    result.round() as u32
+}
+
+fn get_is_on_text(is_on: bool) -> &'static str {
+    if is_on {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn get_is_on_emoji(is_on: bool) -> &'static str {
+    if is_on {
+        "💡"
+    } else {
+        "🌑"
+    }
+}
+
+fn check_serial_number_if_some(serial_number: Option<&str>) -> impl Fn(&Device) -> bool + '_ {
+    move |device| {
+        serial_number.as_ref().map_or(true, |expected| {
+            device
+                .serial_number()
+                .is_some_and(|actual| &actual == expected)
+        })
+    }
+}
+
+#[derive(Debug)]
+enum CliError {
+    DeviceError(DeviceError),
+    SerializationFailed(serde_json::Error),
+    BrightnessPercentageCalculationFailed(TryFromIntError),
+    InvalidBrightness(i16),
+    DeviceNotFound,
+    DeviceInUse(DeviceError, Vec<&'static str>),
+    StdioFailed(std::io::Error),
+    ObsError(String),
+    ProbeFailed(String),
+    InvalidConfig(String, String),
+    SelfTestFailed(usize, usize),
+    DevicesFailed(usize, usize),
+    DmxFailed(String),
+    OscFailed(String),
+    MatchFailed(String),
+    ConditionNotMet,
+    WaitTimedOut(Duration),
+    SnapshotFailed(String),
+    EmulateFailed(String),
+    #[cfg(target_os = "linux")]
+    UdevRulesInstallationFailed(std::io::Error),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::DeviceError(error) => error.fmt(f),
+            CliError::SerializationFailed(error) => error.fmt(f),
+            CliError::BrightnessPercentageCalculationFailed(error) => {
+                write!(f, "Failed to calculate brightness: {}", error)
+            }
+            CliError::InvalidBrightness(brightness) => {
+                write!(f, "Brightness {} lm is not supported", brightness)
+            }
+            CliError::DeviceNotFound => write!(f, "Device not found."),
+            CliError::ObsError(message) => write!(f, "OBS error: {}", message),
+            CliError::ProbeFailed(message) => write!(f, "Failed to probe device: {}", message),
+            CliError::SelfTestFailed(failed, total) => {
+                write!(f, "{} of {} checks failed", failed, total)
+            }
+            CliError::DevicesFailed(failed, total) => {
+                write!(f, "Failed on {} of {} devices", failed, total)
+            }
+            CliError::DmxFailed(message) => write!(f, "DMX error: {}", message),
+            CliError::OscFailed(message) => write!(f, "OSC error: {}", message),
+            CliError::MatchFailed(message) => {
+                write!(f, "Failed to match white point: {}", message)
+            }
+            CliError::ConditionNotMet => write!(f, "Condition not met"),
+            CliError::WaitTimedOut(elapsed) => write!(
+                f,
+                "Gave up waiting for the device after {}",
+                humantime::format_duration(Duration::from_secs(elapsed.as_secs()))
+            ),
+            CliError::SnapshotFailed(message) => write!(f, "Snapshot failed: {}", message),
+            CliError::EmulateFailed(message) => {
+                write!(f, "Failed to emulate devices: {}", message)
+            }
+            CliError::InvalidConfig(path, message) => {
+                write!(f, "Failed to read configuration file {}: {}", path, message)
+            }
+            CliError::StdioFailed(error) => {
+                write!(f, "Failed to read or write standard input or output: {}", error)
+            }
+            CliError::DeviceInUse(error, conflicting_software) => write!(
+                f,
+                "{}. {} appears to be running and may be holding the device. Quit it and try again.",
+                error,
+                conflicting_software.join(" and ")
+            ),
+            #[cfg(target_os = "linux")]
+            CliError::UdevRulesInstallationFailed(error) => {
+                write!(f, "Failed to install udev rules: {}", error)
+            }
+        }
+    }
+}
+
+impl From<DeviceError> for CliError {
+    fn from(error: DeviceError) -> Self {
+        CliError::DeviceError(error)
+    }
+}
+
+type CliResult = Result<(), CliError>;
+
+/// Asks the user a yes or no question on the terminal, returning `true` if they answer yes.
+fn confirm(prompt: &str) -> Result<bool, CliError> {
+    print!("{} [y/N] ", prompt);
+    io::stdout().flush().map_err(CliError::StdioFailed)?;
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(CliError::StdioFailed)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// The number of times to try opening each device when targeting all devices.
+const OPEN_ATTEMPTS: u32 = 4;
+
+/// How long to wait before trying to open devices which failed to open again.
+const OPEN_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Runs an operation on the device or devices targeted by a command. When targeting all devices
+/// from an interactive terminal, the user is asked to confirm first if more than one device would
+/// be changed.
+fn for_each_target_device(
+    options: &config::GlobalOptions,
+    target: &DeviceTarget,
+    operation: impl Fn(&DeviceHandle) -> CliResult,
+) -> CliResult {
+    let context = config::new_context(options)?;
+
+    if !target.all {
+        let device_handle = get_first_supported_device(&context, target.serial_number.as_deref())?;
+        return operation(&device_handle);
+    }
+
+    let devices: Vec<Device> = context.get_connected_devices().collect();
+    if devices.is_empty() {
+        return Err(CliError::DeviceNotFound);
+    }
+
+    if devices.len() > 1 && !target.yes && io::stdin().is_terminal() {
+        println!("This will change the following devices:");
+        for device in &devices {
+            println!("- {}", describe_device(device));
+        }
+        if !confirm("Continue?")? {
+            return Ok(());
+        }
+    }
+
+    // Devices which fail to open are often only held briefly by another process, so they're
+    // retried a few times before giving up, even in strict mode
+    let strict = options.strict;
+    let mut results = Vec::new();
+    let mut pending = devices;
+    for attempt in 1..=OPEN_ATTEMPTS {
+        let mut failed = Vec::new();
+        for device in pending {
+            match device.open(&context) {
+                Ok(device_handle) => match operation(&device_handle) {
+                    Err(error) if strict => return Err(error),
+                    result => results.push((describe_device(&device), result)),
+                },
+                Err(error) if strict && attempt == OPEN_ATTEMPTS => {
+                    return Err(explain_open_error(error))
+                }
+                Err(error) => failed.push((device, error)),
+            }
+        }
+
+        if failed.is_empty() {
+            break;
+        }
+        if attempt == OPEN_ATTEMPTS {
+            results.extend(
+                failed.into_iter().map(|(device, error)| {
+                    (describe_device(&device), Err(explain_open_error(error)))
+                }),
+            );
+            break;
+        }
+
+        thread::sleep(OPEN_RETRY_DELAY);
+        pending = failed.into_iter().map(|(device, _)| device).collect();
+    }
+
+    summarize_device_results(results)
+}
+
+fn describe_device(device: &Device) -> String {
+    format!(
+        "{} ({})",
+        device.device_type(),
+        device.serial_number().unwrap_or("")
+    )
+}
+
+/// Prints whether an operation succeeded on each of several devices, failing if it failed on any
+/// of them. For a single device, its result is returned as it is.
+fn summarize_device_results(mut results: Vec<(String, CliResult)>) -> CliResult {
+    if results.len() == 1 {
+        let (_, result) = results.swap_remove(0);
+        return result;
+    }
+
+    let total = results.len();
+    let mut failed = 0;
+    for (device, result) in results {
+        match result {
+            Ok(()) => println!("- {}: OK", device),
+            Err(error) => {
+                failed += 1;
+                println!("- {}: Failed: {}", device, error);
+            }
+        }
+    }
+
+    if failed > 0 {
+        Err(CliError::DevicesFailed(failed, total))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the device with the given serial number, or the first device if no serial number is
+/// given. If there are multiple devices and we're running in an interactive terminal, the user is
+/// asked to pick one instead.
+fn get_first_supported_device(
+    context: &Litra,
+    serial_number: Option<&str>,
+) -> Result<DeviceHandle, CliError> {
+    if serial_number.is_none() && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        let devices: Vec<Device> = context.get_connected_devices().collect();
+        if devices.len() > 1 {
+            return pick_device(context, &devices);
+        }
+    }
+
+    context
+        .get_connected_devices()
+        .find(check_serial_number_if_some(serial_number))
+        .ok_or(CliError::DeviceNotFound)
+        .and_then(|dev| dev.open(context).map_err(explain_open_error))
+}
+
+/// Asks the user to pick one of the devices with the arrow keys and Enter. Pressing `i` briefly
+/// flashes the highlighted device so it can be identified, and Esc or `q` cancels.
+fn pick_device(context: &Litra, devices: &[Device]) -> Result<DeviceHandle, CliError> {
+    println!("Multiple Logitech Litra devices found. Use the arrow keys and Enter to pick one, or press `i` to identify the highlighted device:");
+    let mut selected = 0;
+    print_device_choices(devices, selected, false)?;
+
+    loop {
+        match read_key()? {
+            KeyCode::Up | KeyCode::Char('k') => {
+                selected = selected.checked_sub(1).unwrap_or(devices.len() - 1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => selected = (selected + 1) % devices.len(),
+            KeyCode::Enter => return devices[selected].open(context).map_err(explain_open_error),
+            KeyCode::Char('i') => {
+                flash(
+                    &devices[selected]
+                        .open(context)
+                        .map_err(explain_open_error)?,
+                )?;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => return Err(CliError::DeviceNotFound),
+            _ => continue,
+        }
+        print_device_choices(devices, selected, true)?;
+    }
+}
+
+/// Prints the devices to pick from, highlighting the selected one. When `redraw` is set, the
+/// previously printed list is overwritten.
+fn print_device_choices(devices: &[Device], selected: usize, redraw: bool) -> CliResult {
+    let mut stdout = io::stdout().lock();
+    if redraw {
+        let lines = u16::try_from(devices.len()).unwrap_or(u16::MAX);
+        crossterm::queue!(stdout, cursor::MoveToPreviousLine(lines))
+            .map_err(CliError::StdioFailed)?;
+    }
+    for (index, device) in devices.iter().enumerate() {
+        let marker = if index == selected { ">" } else { " " };
+        crossterm::queue!(stdout, terminal::Clear(terminal::ClearType::CurrentLine))
+            .map_err(CliError::StdioFailed)?;
+        writeln!(stdout, "{} {}", marker, describe_device(device))
+            .map_err(CliError::StdioFailed)?;
+    }
+    stdout.flush().map_err(CliError::StdioFailed)
+}
+
+/// Waits for a key to be pressed, reading it without waiting for Enter. Ctrl+C is returned as Esc,
+/// since it doesn't interrupt us in raw mode.
+fn read_key() -> Result<KeyCode, CliError> {
+    terminal::enable_raw_mode().map_err(CliError::StdioFailed)?;
+    let event = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break Ok(key),
+            Ok(_) => {}
+            Err(error) => break Err(error),
+        }
+    };
+    terminal::disable_raw_mode().map_err(CliError::StdioFailed)?;
+
+    let key = event.map_err(CliError::StdioFailed)?;
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        return Ok(KeyCode::Esc);
+    }
+    Ok(key.code)
+}
+
+/// Briefly flashes a device by toggling it twice, leaving it in its original state.
+fn flash(device_handle: &DeviceHandle) -> CliResult {
+    let is_on = device_handle.is_on()?;
+    for on in [!is_on, is_on, !is_on, is_on] {
+        device_handle.set_on(on)?;
+        thread::sleep(Duration::from_millis(300));
+    }
+    Ok(())
+}
+
+/// Logitech's own software, along with substrings of the names of its processes. These apps can
+/// hold Litra devices open, which causes our attempts to open them to fail.
+const CONFLICTING_SOFTWARE: [(&str, &str); 2] = [
+    ("Logitech Options+", "logioptionsplus"),
+    ("Logitech G HUB", "lghub"),
+];
+
+fn list_running_process_names() -> Option<String> {
+    let output = if cfg!(target_os = "windows") {
+        std::process::Command::new("tasklist")
+            .args(["/fo", "csv", "/nh"])
+            .output()
+    } else {
+        std::process::Command::new("ps")
+            .args(["-A", "-o", "comm="])
+            .output()
+    }
+    .ok()?;
+
+    Some(String::from_utf8_lossy(&output.stdout).to_lowercase())
+}
+
+fn detect_conflicting_software() -> Vec<&'static str> {
+    let Some(process_names) = list_running_process_names() else {
+        return Vec::new();
+    };
+
+    CONFLICTING_SOFTWARE
+        .iter()
+        .filter(|(_, process_name)| process_names.contains(process_name))
+        .map(|(name, _)| *name)
+        .collect()
+}
+
+fn explain_open_error(error: DeviceError) -> CliError {
+    if !matches!(error, DeviceError::HidError(_)) {
+        return CliError::DeviceError(error);
+    }
+
+    let conflicting_software = detect_conflicting_software();
+    if conflicting_software.is_empty() {
+        CliError::DeviceError(error)
+    } else {
+        CliError::DeviceInUse(error, conflicting_software)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+struct DeviceInfo {
+    pub serial_number: String,
+    pub device_type: String,
+    pub is_on: bool,
+    pub brightness_in_lumen: u16,
+    pub temperature_in_kelvin: u16,
+    pub minimum_brightness_in_lumen: u16,
+    pub maximum_brightness_in_lumen: u16,
+    pub minimum_temperature_in_kelvin: u16,
+    pub maximum_temperature_in_kelvin: u16,
+    pub firmware_version: Option<String>,
+}
+
+fn get_device_info(serial_number: &str, device_handle: &DeviceHandle) -> Option<DeviceInfo> {
+    read_device_info(serial_number, device_handle).ok()
+}
+
+/// Reads the state of a device like [`get_device_info`], returning the error if it fails.
+fn read_device_info(serial_number: &str, device_handle: &DeviceHandle) -> DeviceResult<DeviceInfo> {
+    Ok(DeviceInfo {
+        serial_number: serial_number.to_string(),
+        device_type: device_handle.device_type().to_string(),
+        is_on: device_handle.is_on()?,
+        brightness_in_lumen: device_handle.brightness_in_lumen()?,
+        temperature_in_kelvin: device_handle.temperature_in_kelvin()?,
+        minimum_brightness_in_lumen: device_handle.minimum_brightness_in_lumen(),
+        maximum_brightness_in_lumen: device_handle.maximum_brightness_in_lumen(),
+        minimum_temperature_in_kelvin: device_handle.minimum_temperature_in_kelvin(),
+        maximum_temperature_in_kelvin: device_handle.maximum_temperature_in_kelvin(),
+        firmware_version: device_handle
+            .firmware_version()
+            .ok()
+            .map(|firmware_version| firmware_version.to_string()),
+    })
+}
+
+#[derive(Serialize, Debug)]
+struct UnsupportedDeviceInfo {
+    pub product_id: String,
+    pub product_name: Option<String>,
+    pub serial_number: Option<String>,
+    pub path: String,
+}
+
+fn get_litra_devices(context: &Litra) -> Vec<DeviceInfo> {
+    context
+        .get_connected_devices()
+        .filter_map(|device| {
+            let device_handle = device.open(context).ok()?;
+            get_device_info(device.serial_number().unwrap_or(""), &device_handle)
+        })
+        .collect()
+}
+
+fn print_litra_devices(litra_devices: &[DeviceInfo]) {
+    if litra_devices.is_empty() {
+        println!("No Logitech Litra devices found");
+    } else {
+        for device_info in litra_devices {
+            println!(
+                "- {} ({}): {} {}",
+                device_info.device_type,
+                device_info.serial_number,
+                get_is_on_text(device_info.is_on),
+                get_is_on_emoji(device_info.is_on)
+            );
+
+            println!("  - Brightness: {} lm", device_info.brightness_in_lumen);
+            println!(
+                "    - Minimum: {} lm",
+                device_info.minimum_brightness_in_lumen
+            );
+            println!(
+                "    - Maximum: {} lm",
+                device_info.maximum_brightness_in_lumen
+            );
+            println!("  - Temperature: {} K", device_info.temperature_in_kelvin);
+            println!(
+                "    - Minimum: {} K",
+                device_info.minimum_temperature_in_kelvin
+            );
+            println!(
+                "    - Maximum: {} K",
+                device_info.maximum_temperature_in_kelvin
+            );
+            if let Some(firmware_version) = &device_info.firmware_version {
+                println!("  - Firmware: {}", firmware_version);
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PolledDeviceInfo<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    device_info: &'a DeviceInfo,
+}
+
+/// Repeatedly reads and prints the state of connected devices until interrupted. With `json`, each
+/// device's state is printed as a line of JSON, along with the time it was read.
+fn poll_devices(context: &mut Litra, json: bool, interval: Duration) -> CliResult {
+    loop {
+        context.refresh_connected_devices()?;
+        let litra_devices = get_litra_devices(context);
+        let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+
+        if json {
+            for device_info in &litra_devices {
+                let output = serde_json::to_string(&PolledDeviceInfo {
+                    timestamp: timestamp.clone(),
+                    device_info,
+                })
+                .map_err(CliError::SerializationFailed)?;
+                println!("{}", output);
+            }
+        } else {
+            println!("{}", timestamp);
+            print_litra_devices(&litra_devices);
+        }
+        io::stdout().flush().map_err(CliError::StdioFailed)?;
+
+        thread::sleep(interval);
+    }
+}
+
+/// Returns the output of `litra devices --json`. With `--all`, the devices are wrapped in an object
+/// alongside the unsupported devices.
+fn devices_json(
+    litra_devices: &[DeviceInfo],
+    unsupported_devices: Option<&[UnsupportedDeviceInfo]>,
+) -> Result<serde_json::Value, serde_json::Error> {
+    match unsupported_devices {
+        Some(unsupported_devices) => Ok(serde_json::json!({
+            "devices": litra_devices,
+            "unsupported": unsupported_devices,
+        })),
+        None => serde_json::to_value(litra_devices),
+    }
+}
+
+fn handle_devices_command(
+    options: &config::GlobalOptions,
+    json: bool,
+    all: bool,
+    poll: Option<Duration>,
+) -> CliResult {
+    let mut context = config::new_context(options)?;
+    if let Some(interval) = poll {
+        return poll_devices(&mut context, json, interval);
+    }
+
+    let litra_devices = get_litra_devices(&context);
+    let unsupported_devices: Vec<UnsupportedDeviceInfo> = if all {
+        context
+            .get_unrecognized_devices()
+            .map(|device_info| UnsupportedDeviceInfo {
+                product_id: format!("0x{:04x}", device_info.product_id()),
+                product_name: device_info.product_string().map(String::from),
+                serial_number: device_info.serial_number().map(String::from),
+                path: device_info.path().to_string_lossy().into_owned(),
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    if json {
+        let output = devices_json(&litra_devices, all.then_some(&unsupported_devices[..]))
+            .map_err(CliError::SerializationFailed)?;
+        println!("{}", output);
+        Ok(())
+    } else {
+        print_litra_devices(&litra_devices);
+
+        if all {
+            if unsupported_devices.is_empty() {
+                println!("No unsupported Logitech devices found");
+            } else {
+                println!("Unsupported Logitech devices:");
+                for device_info in &unsupported_devices {
+                    println!(
+                        "- {} ({}): product ID {}",
+                        device_info.product_name.as_deref().unwrap_or("Unknown"),
+                        device_info
+                            .serial_number
+                            .as_deref()
+                            .unwrap_or("no serial number"),
+                        device_info.product_id
+                    );
+                    println!("  - Path: {}", device_info.path);
+                }
+                println!(
+                    "To help add support for these devices, run `litra probe --device-path <path>` and open an issue at https://github.com/timrogers/litra-rs/issues including the output."
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn handle_on_command(options: &config::GlobalOptions, target: &DeviceTarget) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        device_handle.set_on(true)?;
+        Ok(())
+    })
+}
+
+fn handle_off_command(options: &config::GlobalOptions, target: &DeviceTarget) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        device_handle.set_on(false)?;
+        Ok(())
+    })
+}
+
+fn handle_toggle_command(options: &config::GlobalOptions, target: &DeviceTarget) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        let is_on = device_handle.is_on()?;
+        device_handle.set_on(!is_on)?;
+        Ok(())
+    })
+}
+
+fn handle_is_on_command(
+    options: &config::GlobalOptions,
+    serial_number: Option<&str>,
+    any: bool,
+    all: bool,
+) -> CliResult {
+    let context = config::new_context(options)?;
+    let mut devices = context
+        .get_connected_devices()
+        .filter(check_serial_number_if_some(serial_number))
+        .peekable();
+    if devices.peek().is_none() {
+        return Err(CliError::DeviceNotFound);
+    }
+
+    let is_on = |device: Device| -> Result<bool, CliError> {
+        let device_handle = device.open(&context).map_err(explain_open_error)?;
+        Ok(device_handle.is_on()?)
+    };
+    let result = if any {
+        devices
+            .map(is_on)
+            .collect::<Result<Vec<_>, _>>()?
+            .contains(&true)
+    } else if all {
+        !devices
+            .map(is_on)
+            .collect::<Result<Vec<_>, _>>()?
+            .contains(&false)
+    } else {
+        devices.next().map(is_on).transpose()?.unwrap_or(false)
+    };
+
+    if result {
+        Ok(())
+    } else {
+        Err(CliError::ConditionNotMet)
+    }
+}
+
+/// The exit code of `is-on` when the state of the device couldn't be checked.
+const IS_ON_ERROR_EXIT_CODE: u8 = 2;
+
+const DEFAULT_STATUS_TEMPLATE: &str = "{emoji} {brightness}lm {temperature}K";
+
+fn render_status_template(template: &str, device_info: &DeviceInfo) -> String {
+    // Devices from the configuration file can have any range, and may report a brightness outside
+    // of it, so the percentage is clamped rather than allowed to underflow
+    let brightness_range = device_info
+        .maximum_brightness_in_lumen
+        .saturating_sub(device_info.minimum_brightness_in_lumen);
+    let brightness_percentage = if brightness_range == 0 {
+        100
+    } else {
+        (u32::from(
+            device_info
+                .brightness_in_lumen
+                .saturating_sub(device_info.minimum_brightness_in_lumen),
+        ) * 100
+            / u32::from(brightness_range))
+        .min(100)
+    };
+
+    template
+        .replace("{emoji}", get_is_on_emoji(device_info.is_on))
+        .replace("{power}", get_is_on_text(device_info.is_on))
+        .replace("{brightness}", &device_info.brightness_in_lumen.to_string())
+        .replace(
+            "{brightness_percentage}",
+            &brightness_percentage.to_string(),
+        )
+        .replace(
+            "{temperature}",
+            &device_info.temperature_in_kelvin.to_string(),
+        )
+        .replace("{device_type}", &device_info.device_type)
+        .replace("{serial_number}", &device_info.serial_number)
+}
+
+fn handle_status_command(
+    options: &config::GlobalOptions,
+    serial_number: Option<&str>,
+    short: bool,
+    template: &str,
+) -> CliResult {
+    let context = config::new_context(options)?;
+    let device = context
+        .get_connected_devices()
+        .find(check_serial_number_if_some(serial_number))
+        .ok_or(CliError::DeviceNotFound)?;
+    let device_handle = device.open(&context).map_err(explain_open_error)?;
+    let device_info = DeviceInfo {
+        serial_number: device.serial_number().unwrap_or("").to_string(),
+        device_type: device_handle.device_type().to_string(),
+        is_on: device_handle.is_on()?,
+        brightness_in_lumen: device_handle.brightness_in_lumen()?,
+        temperature_in_kelvin: device_handle.temperature_in_kelvin()?,
+        minimum_brightness_in_lumen: device_handle.minimum_brightness_in_lumen(),
+        maximum_brightness_in_lumen: device_handle.maximum_brightness_in_lumen(),
+        minimum_temperature_in_kelvin: device_handle.minimum_temperature_in_kelvin(),
+        maximum_temperature_in_kelvin: device_handle.maximum_temperature_in_kelvin(),
+        firmware_version: None,
+    };
+
+    if short {
+        println!("{}", render_status_template(template, &device_info));
+    } else {
+        print_litra_devices(&[device_info]);
+    }
+    Ok(())
+}
+
+struct WaitCondition {
+    on: Option<bool>,
+    brightness_at_least: Option<u16>,
+}
+
+impl WaitCondition {
+    fn is_met(&self, device_handle: &DeviceHandle) -> Result<bool, CliError> {
+        if let Some(on) = self.on {
+            if device_handle.is_on()? != on {
+                return Ok(false);
+            }
+        }
+        if let Some(brightness_in_lumen) = self.brightness_at_least {
+            if device_handle.brightness_in_lumen()? < brightness_in_lumen {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn handle_wait_until_command(
+    options: &config::GlobalOptions,
+    serial_number: Option<&str>,
+    condition: WaitCondition,
+    max_wait: Option<Duration>,
+    poll_interval: Duration,
+) -> CliResult {
+    let context = config::new_context(options)?;
+    let device_handle = context
+        .get_connected_devices()
+        .find(check_serial_number_if_some(serial_number))
+        .ok_or(CliError::DeviceNotFound)?
+        .open(&context)
+        .map_err(explain_open_error)?;
+
+    let started_at = Instant::now();
+    while !condition.is_met(&device_handle)? {
+        if max_wait.is_some_and(|max_wait| started_at.elapsed() >= max_wait) {
+            return Err(CliError::WaitTimedOut(started_at.elapsed()));
+        }
+        thread::sleep(poll_interval);
+    }
+    Ok(())
+}
+
+fn handle_brightness_command(
+    options: &config::GlobalOptions,
+    target: &DeviceTarget,
+    value: Option<u16>,
+    percentage: Option<u8>,
+) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        match (value, percentage) {
+            (Some(_), None) => {
+                let brightness_in_lumen = value.unwrap();
+                device_handle.set_brightness_in_lumen(brightness_in_lumen)?;
+            }
+            (None, Some(_)) => {
+                let brightness_in_lumen = percentage_within_range(
+                    percentage.unwrap().into(),
+                    device_handle.minimum_brightness_in_lumen().into(),
+                    device_handle.maximum_brightness_in_lumen().into(),
+                )
+                .try_into()
+                .map_err(CliError::BrightnessPercentageCalculationFailed)?;
+
+                device_handle.set_brightness_in_lumen(brightness_in_lumen)?;
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    })
+}
+
+fn handle_brightness_up_command(
+    options: &config::GlobalOptions,
+    target: &DeviceTarget,
+    value: Option<u16>,
+    percentage: Option<u8>,
+) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        let current_brightness = device_handle.brightness_in_lumen()?;
+
+        match (value, percentage) {
+            (Some(_), None) => {
+                let brightness_to_add = value.unwrap();
+                let new_brightness = current_brightness + brightness_to_add;
+                device_handle.set_brightness_in_lumen(new_brightness)?;
+            }
+            (None, Some(_)) => {
+                let brightness_to_add = percentage_within_range(
+                    percentage.unwrap().into(),
+                    device_handle.minimum_brightness_in_lumen().into(),
+                    device_handle.maximum_brightness_in_lumen().into(),
+                ) as u16
+                    - device_handle.minimum_brightness_in_lumen();
+
+                let new_brightness = current_brightness + brightness_to_add;
+
+                device_handle.set_brightness_in_lumen(new_brightness)?;
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    })
+}
+
+fn handle_brightness_down_command(
+    options: &config::GlobalOptions,
+    target: &DeviceTarget,
+    value: Option<u16>,
+    percentage: Option<u8>,
+) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        let current_brightness = device_handle.brightness_in_lumen()?;
+
+        match (value, percentage) {
+            (Some(_), None) => {
+                let brightness_to_subtract = value.unwrap();
+                let new_brightness = current_brightness - brightness_to_subtract;
+                device_handle.set_brightness_in_lumen(new_brightness)?;
+            }
+            (None, Some(_)) => {
+                let brightness_to_subtract = percentage_within_range(
+                    percentage.unwrap().into(),
+                    device_handle.minimum_brightness_in_lumen().into(),
+                    device_handle.maximum_brightness_in_lumen().into(),
+                ) as u16
+                    - device_handle.minimum_brightness_in_lumen();
+
+                let new_brightness = current_brightness as i16 - brightness_to_subtract as i16;
+
+                if new_brightness < 0 {
+                    Err(CliError::InvalidBrightness(new_brightness))?;
+                }
+
+                device_handle.set_brightness_in_lumen(new_brightness as u16)?;
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    })
+}
+
+fn handle_temperature_command(
+    options: &config::GlobalOptions,
+    target: &DeviceTarget,
+    value: u16,
+) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        device_handle.set_temperature_in_kelvin(value)?;
+        Ok(())
+    })
+}
+
+fn handle_temperature_up_command(
+    options: &config::GlobalOptions,
+    target: &DeviceTarget,
+    value: u16,
+) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        let current_temperature = device_handle.temperature_in_kelvin()?;
+        let new_temperature = current_temperature + value;
+
+        device_handle.set_temperature_in_kelvin(new_temperature)?;
+        Ok(())
+    })
+}
+
+fn handle_temperature_down_command(
+    options: &config::GlobalOptions,
+    target: &DeviceTarget,
+    value: u16,
+) -> CliResult {
+    for_each_target_device(options, target, |device_handle| {
+        let current_temperature = device_handle.temperature_in_kelvin()?;
+        let new_temperature = current_temperature - value;
+
+        device_handle.set_temperature_in_kelvin(new_temperature)?;
+        Ok(())
+    })
+}
+
+#[cfg(target_os = "linux")]
+const UDEV_RULES_PATH: &str = "/etc/udev/rules.d/99-litra.rules";
+
+#[cfg(target_os = "linux")]
+fn generate_udev_rules() -> String {
+    litra::PRODUCT_IDS
+        .iter()
+        .map(|(product_id, _)| {
+            format!(
+                "SUBSYSTEM==\"hidraw\", ATTRS{{idVendor}}==\"{:04x}\", ATTRS{{idProduct}}==\"{:04x}\", GROUP=\"video\", MODE=\"0660\", TAG+=\"uaccess\"\n",
+                litra::VENDOR_ID,
+                product_id
+            )
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn run_udev_command(use_sudo: bool, args: &[&str], stdin: Option<&str>) -> CliResult {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let (program, args) = if use_sudo {
+        ("sudo", args)
+    } else {
+        (args[0], &args[1..])
+    };
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::inherit()
+        })
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(CliError::UdevRulesInstallationFailed)?;
+
+    if let (Some(input), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+        child_stdin
+            .write_all(input.as_bytes())
+            .map_err(CliError::UdevRulesInstallationFailed)?;
+    }
+
+    let status = child
+        .wait()
+        .map_err(CliError::UdevRulesInstallationFailed)?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(CliError::UdevRulesInstallationFailed(
+            std::io::Error::other(format!("`{}` exited with {}", args.join(" "), status)),
+        ))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn handle_udev_install_command(print: bool) -> CliResult {
+    let rules = generate_udev_rules();
+
+    if print {
+        print!("{}", rules);
+        return Ok(());
+    }
+
+    let use_sudo = match std::fs::write(UDEV_RULES_PATH, &rules) {
+        Ok(()) => false,
+        Err(error) if error.kind() == std::io::ErrorKind::PermissionDenied => {
+            eprintln!(
+                "Administrator privileges are required to write {}. Retrying with sudo...",
+                UDEV_RULES_PATH
+            );
+            run_udev_command(true, &["tee", UDEV_RULES_PATH], Some(&rules))?;
+            true
+        }
+        Err(error) => return Err(CliError::UdevRulesInstallationFailed(error)),
+    };
+
+    run_udev_command(use_sudo, &["udevadm", "control", "--reload-rules"], None)?;
+    run_udev_command(use_sudo, &["udevadm", "trigger"], None)?;
+
+    println!(
+        "Installed udev rules to {}. You may need to unplug and reconnect your devices.",
+        UDEV_RULES_PATH
+    );
+    Ok(())
+}
+
+fn handle_config_validate_command() -> CliResult {
+    let Some(path) = config::config_path() else {
+        println!("No configuration directory found");
+        return Ok(());
+    };
+    if !path.exists() {
+        println!("No configuration file found at {}", path.display());
+        return Ok(());
+    }
+
+    let problems = config::validate_config(&config::load_config()?, is_built_in_command, |args| {
+        Cli::try_parse_from(std::iter::once("litra").chain(args.iter().copied()))
+            .map(|_| ())
+            .map_err(|error| {
+                let message = error.to_string();
+                let first_line = message.lines().next().unwrap_or_default();
+                first_line.trim_start_matches("error: ").to_string()
+            })
+    });
+    if problems.is_empty() {
+        println!("{} is valid", path.display());
+        return Ok(());
+    }
+
+    for problem in &problems {
+        println!("- {}", problem);
+    }
+    Err(CliError::InvalidConfig(
+        path.display().to_string(),
+        format!("{} problem(s) found", problems.len()),
+    ))
+}
+
+fn handle_config_schema_command() -> CliResult {
+    let schema = serde_json::to_string_pretty(&config::config_schema())
+        .map_err(CliError::SerializationFailed)?;
+    println!("{}", schema);
+    Ok(())
+}
+
+fn is_built_in_command(name: &str) -> bool {
+    Cli::command().find_subcommand(name).is_some()
+}
+
+/// Finds the value of a `--serial-number`/`-s` option in a plugin's arguments, so that the selected
+/// device can be passed on to it. Arguments after `--` are left alone.
+fn find_serial_number_argument(args: &[OsString]) -> Option<OsString> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--") => return None,
+            Some("--serial-number" | "-s") => return args.next().cloned(),
+            Some(arg) => {
+                if let Some(serial_number) = arg.strip_prefix("--serial-number=") {
+                    return Some(OsString::from(serial_number));
+                }
+            }
+            None => {}
+        }
+    }
+    None
+}
+
+/// Runs an external plugin command, following the same convention as Cargo and Git: if `litra foo`
+/// isn't a built-in command, `litra-foo` is run from the `PATH` with the remaining arguments.
+/// Returns `None` if the command isn't a plugin.
+fn run_plugin(args: &[OsString]) -> Option<ExitCode> {
+    let name = args.get(1)?.to_str()?;
+    if name.starts_with('-') || is_built_in_command(name) {
+        return None;
+    }
+
+    let mut command = Command::new(format!("litra-{}", name));
+    command.args(&args[2..]);
+    if let Ok(executable) = std::env::current_exe() {
+        command.env("LITRA", executable);
+    }
+    if let Some(config_path) = config::config_path() {
+        command.env("LITRA_CONFIG", config_path);
+    }
+    if let Some(serial_number) = find_serial_number_argument(&args[2..]) {
+        command.env("LITRA_SERIAL_NUMBER", serial_number);
+    }
+
+    match command.status() {
+        Ok(status) => Some(match status.code() {
+            Some(code) => ExitCode::from(u8::try_from(code).unwrap_or(1)),
+            None => ExitCode::FAILURE,
+        }),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => {
+            eprintln!("Failed to run litra-{}: {}", name, error);
+            Some(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// Runs a built-in command.
+fn run_command(command: &Commands, options: &config::GlobalOptions) -> CliResult {
+    match command {
+        Commands::Devices { json, all, poll } => {
+            handle_devices_command(options, *json, *all, *poll)
+        }
+        Commands::On { target } => handle_on_command(options, target),
+        Commands::Off { target } => handle_off_command(options, target),
+        Commands::Toggle { target } => handle_toggle_command(options, target),
+        Commands::Status {
+            serial_number,
+            short,
+            template,
+        } => handle_status_command(options, serial_number.as_deref(), *short, template),
+        Commands::WaitUntil {
+            serial_number,
+            on,
+            off,
+            brightness_at_least,
+            max_wait,
+            poll_interval_ms,
+        } => handle_wait_until_command(
+            options,
+            serial_number.as_deref(),
+            WaitCondition {
+                on: (*on || *off).then_some(*on),
+                brightness_at_least: *brightness_at_least,
+            },
+            *max_wait,
+            Duration::from_millis(*poll_interval_ms),
+        ),
+        Commands::IsOn {
+            serial_number,
+            any,
+            all,
+        } => handle_is_on_command(options, serial_number.as_deref(), *any, *all),
+        Commands::Brightness {
+            target,
+            value,
+            percentage,
+        } => handle_brightness_command(options, target, *value, *percentage),
+        Commands::BrightnessUp {
+            target,
+            value,
+            percentage,
+        } => handle_brightness_up_command(options, target, *value, *percentage),
+        Commands::BrightnessDown {
+            target,
+            value,
+            percentage,
+        } => handle_brightness_down_command(options, target, *value, *percentage),
+        Commands::Temperature { target, value } => {
+            handle_temperature_command(options, target, *value)
+        }
+        Commands::TemperatureUp { target, value } => {
+            handle_temperature_up_command(options, target, *value)
+        }
+        Commands::TemperatureDown { target, value } => {
+            handle_temperature_down_command(options, target, *value)
+        }
+        Commands::Match {
+            target,
+            white_point,
+            icc_profile,
+            display,
+        } => white_point::handle_match_command(
+            options,
+            target,
+            *white_point,
+            icc_profile.as_deref(),
+            *display,
+        ),
+        Commands::Rpc { poll_interval_ms } => {
+            rpc::handle_rpc_command(options, Duration::from_millis(*poll_interval_ms))
+        }
+        Commands::Obs {
+            serial_number,
+            url,
+            password,
+            brightness,
+            temperature,
+        } => obs::handle_obs_command(
+            options,
+            url,
+            password.as_deref(),
+            obs::LiveOptions {
+                serial_number: serial_number.as_deref(),
+                brightness_in_lumen: *brightness,
+                temperature_in_kelvin: *temperature,
+            },
+        ),
+        Commands::Bench {
+            serial_number,
+            iterations,
+        } => bench::handle_bench_command(options, serial_number.as_deref(), *iterations),
+        Commands::SelfTest { serial_number } => {
+            self_test::handle_self_test_command(options, serial_number.as_deref())
+        }
+        Commands::Stress {
+            serial_number,
+            duration,
+            ops_per_second,
+        } => stress::handle_stress_command(
+            options,
+            serial_number.as_deref(),
+            *duration,
+            *ops_per_second,
+        ),
+        Commands::Probe { device_path, yes } => probe::handle_probe_command(device_path, *yes),
+        Commands::Sync {
+            master,
+            serial_number,
+            poll_interval_ms,
+        } => sync::handle_sync_command(
+            options,
+            master,
+            serial_number,
+            Duration::from_millis(*poll_interval_ms),
+        ),
+        Commands::Pin {
+            serial_number,
+            brightness,
+            temperature,
+            duration,
+            poll_interval_ms,
+        } => pin::handle_pin_command(
+            options,
+            serial_number.as_deref(),
+            *brightness,
+            *temperature,
+            *duration,
+            Duration::from_millis(*poll_interval_ms),
+        ),
+        Commands::Dmx {
+            protocol,
+            universe,
+            address,
+            serial_number,
+        } => dmx::handle_dmx_command(options, *protocol, *universe, *address, serial_number),
+        Commands::Osc { port } => osc::handle_osc_command(options, *port),
+        Commands::Config { command } => match command {
+            ConfigCommands::Validate => handle_config_validate_command(),
+            ConfigCommands::Schema => handle_config_schema_command(),
+        },
+        Commands::Schema { output } => schema::handle_schema_command(*output),
+        Commands::Snapshot { command } => match command {
+            SnapshotCommands::Save { name } => {
+                snapshot::handle_snapshot_save_command(options, name)
+            }
+            SnapshotCommands::Restore { name } => {
+                snapshot::handle_snapshot_restore_command(options, name)
+            }
+            SnapshotCommands::List => snapshot::handle_snapshot_list_command(),
+        },
+        #[cfg(target_os = "linux")]
+        Commands::Udev {
+            command: UdevCommands::Install { print },
+        } => handle_udev_install_command(*print),
+        Commands::Emulate {
+            device_type,
+            count,
+            command,
+        } => emulate::handle_emulate_command(*device_type, *count, command),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match config::expand_alias(std::env::args_os().collect(), is_built_in_command) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Some(exit_code) = run_plugin(&args) {
+        return exit_code;
+    }
+    let args = Cli::parse_from(args);
+    let options = config::GlobalOptions {
+        timeout: args.timeout,
+        retries: args.retries,
+        strict: args.strict,
+        simulated_devices: Vec::new(),
+    };
+
+    let result = run_command(&args.command, &options);
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::ConditionNotMet) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("{}", error);
+            // `is-on` uses 1 to mean that the device is off, so errors need a different code
+            if matches!(args.command, Commands::IsOn { .. }) {
+                ExitCode::from(IS_ON_ERROR_EXIT_CODE)
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device_info(brightness_in_lumen: u16, minimum: u16, maximum: u16) -> DeviceInfo {
+        DeviceInfo {
+            serial_number: "2325FE7044P8".to_string(),
+            device_type: "Litra Glow".to_string(),
+            is_on: true,
+            brightness_in_lumen,
+            temperature_in_kelvin: 4500,
+            minimum_brightness_in_lumen: minimum,
+            maximum_brightness_in_lumen: maximum,
+            minimum_temperature_in_kelvin: 2700,
+            maximum_temperature_in_kelvin: 6500,
+            firmware_version: None,
+        }
+    }
+
+    #[test]
+    fn finds_the_serial_number_in_plugin_arguments() {
+        let find = |args: &[&str]| {
+            let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+            find_serial_number_argument(&args)
+        };
+        assert_eq!(
+            find(&["--serial-number", "ABC", "--verbose"]),
+            Some(OsString::from("ABC"))
+        );
+        assert_eq!(
+            find(&["--verbose", "-s", "ABC"]),
+            Some(OsString::from("ABC"))
+        );
+        assert_eq!(find(&["--serial-number=ABC"]), Some(OsString::from("ABC")));
+        assert_eq!(find(&["--verbose"]), None);
+        assert_eq!(find(&["--serial-number"]), None);
+        assert_eq!(find(&["--", "--serial-number", "ABC"]), None);
+    }
+
+    #[test]
+    fn renders_the_default_status_template() {
+        assert_eq!(
+            render_status_template(DEFAULT_STATUS_TEMPLATE, &device_info(135, 20, 250)),
+            "💡 135lm 4500K"
+        );
+    }
+
+    #[test]
+    fn renders_every_status_placeholder() {
+        let mut device_info = device_info(135, 20, 250);
+        device_info.is_on = false;
+        assert_eq!(
+            render_status_template(
+                "{emoji} {power} {brightness} {brightness_percentage}% {temperature} {device_type} {serial_number} {unknown}",
+                &device_info
+            ),
+            "🌑 Off 135 50% 4500 Litra Glow 2325FE7044P8 {unknown}"
+        );
+    }
+
+    #[test]
+    fn renders_brightness_percentages_at_the_ends_of_the_range() {
+        let render = |device_info| render_status_template("{brightness_percentage}", &device_info);
+        assert_eq!(render(device_info(20, 20, 250)), "0");
+        assert_eq!(render(device_info(250, 20, 250)), "100");
+        assert_eq!(render(device_info(100, 100, 100)), "100");
+    }
+
+    #[test]
+    fn renders_brightness_percentages_outside_of_the_range() {
+        let render = |device_info| render_status_template("{brightness_percentage}", &device_info);
+        assert_eq!(render(device_info(10, 20, 250)), "0");
+        assert_eq!(render(device_info(300, 20, 250)), "100");
+        assert_eq!(render(device_info(100, 250, 20)), "100");
+    }
+}
@@ -0,0 +1,343 @@
+//! Watches OBS Studio using [obs-websocket](https://github.com/obsproject/obs-websocket) (version
+//! 5, built into OBS 28 and later), turning a Litra device on while OBS is streaming, recording or
+//! running its virtual camera, and restoring its previous state afterwards.
+
+use crate::{config, get_first_supported_device, CliError, CliResult};
+use data_encoding::BASE64;
+use litra::{DeviceError, DeviceHandle};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::io;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+const OP_HELLO: u64 = 0;
+const OP_IDENTIFY: u64 = 1;
+const OP_IDENTIFIED: u64 = 2;
+const OP_EVENT: u64 = 5;
+const OP_REQUEST: u64 = 6;
+const OP_REQUEST_RESPONSE: u64 = 7;
+
+/// The `Outputs` event subscription, which covers streaming, recording and the virtual camera.
+const EVENT_SUBSCRIPTION_OUTPUTS: u64 = 1 << 6;
+
+/// How often to check whether Ctrl+C has been pressed while waiting for messages from OBS.
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Set when Ctrl+C is pressed, so that we stop watching OBS and restore the device before exiting.
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// The outputs we watch, as the request used to get their status and the event sent when it
+/// changes.
+const OUTPUTS: [(&str, &str); 3] = [
+    ("GetStreamStatus", "StreamStateChanged"),
+    ("GetRecordStatus", "RecordStateChanged"),
+    ("GetVirtualCamStatus", "VirtualcamStateChanged"),
+];
+
+/// The options to apply to the device while OBS is live.
+#[derive(Debug)]
+pub struct LiveOptions<'a> {
+    pub serial_number: Option<&'a str>,
+    pub brightness_in_lumen: Option<u16>,
+    pub temperature_in_kelvin: Option<u16>,
+}
+
+/// The state of the device before we changed it, so it can be restored.
+#[derive(Debug)]
+struct SavedState {
+    is_on: bool,
+    brightness_in_lumen: u16,
+    temperature_in_kelvin: u16,
+}
+
+fn obs_error(error: impl std::fmt::Display) -> CliError {
+    CliError::ObsError(error.to_string())
+}
+
+fn read_message(socket: &mut Socket) -> Result<Value, CliError> {
+    loop {
+        if let Some(message) = parse_message(socket.read().map_err(obs_error)?)? {
+            return Ok(message);
+        }
+    }
+}
+
+/// Reads the next message from OBS like [`read_message`], but returns `None` once Ctrl+C has been
+/// pressed. The socket needs a read timeout, so that this is checked regularly. Reads interrupted by
+/// Ctrl+C are treated like timeouts.
+fn read_message_until_stopped(socket: &mut Socket) -> Result<Option<Value>, CliError> {
+    loop {
+        if STOP_REQUESTED.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        match socket.read() {
+            Ok(message) => {
+                if let Some(message) = parse_message(message)? {
+                    return Ok(Some(message));
+                }
+            }
+            Err(tungstenite::Error::Io(error))
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                ) => {}
+            Err(error) => return Err(obs_error(error)),
+        }
+    }
+}
+
+/// Parses a text message from OBS, returning `None` for other kinds of message.
+fn parse_message(message: Message) -> Result<Option<Value>, CliError> {
+    match message {
+        Message::Text(text) => serde_json::from_str(&text)
+            .map(Some)
+            .map_err(CliError::SerializationFailed),
+        Message::Close(_) => Err(obs_error("OBS closed the connection")),
+        _ => Ok(None),
+    }
+}
+
+/// Makes Ctrl+C set [`STOP_REQUESTED`] rather than exiting straight away, so that the device can be
+/// restored.
+fn handle_ctrl_c() -> CliResult {
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    match ctrlc::set_handler(|| STOP_REQUESTED.store(true, Ordering::SeqCst)) {
+        // The handler is already installed if this command has run before in this process, e.g.
+        // under `litra emulate`
+        Ok(()) | Err(ctrlc::Error::MultipleHandlers) => Ok(()),
+        Err(error) => Err(obs_error(error)),
+    }
+}
+
+fn send_message(socket: &mut Socket, op: u64, data: Value) -> CliResult {
+    socket
+        .send(Message::Text(json!({ "op": op, "d": data }).to_string()))
+        .map_err(obs_error)
+}
+
+fn authentication_string(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = BASE64.encode(&Sha256::digest(format!("{}{}", password, salt)));
+    BASE64.encode(&Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+fn connect(url: &str, password: Option<&str>) -> Result<Socket, CliError> {
+    let (mut socket, _) = tungstenite::connect(url).map_err(obs_error)?;
+
+    let hello = read_message(&mut socket)?;
+    if hello["op"] != OP_HELLO {
+        return Err(obs_error("Expected a Hello message from OBS"));
+    }
+
+    let mut identify = json!({
+        "rpcVersion": 1,
+        "eventSubscriptions": EVENT_SUBSCRIPTION_OUTPUTS,
+    });
+    if let Some(authentication) = hello["d"].get("authentication") {
+        let password = password.ok_or_else(|| {
+            obs_error(
+                "OBS requires a password. Pass it with the `--password` argument or the `LITRA_OBS_PASSWORD` environment variable",
+            )
+        })?;
+        identify["authentication"] = json!(authentication_string(
+            password,
+            authentication["salt"].as_str().unwrap_or(""),
+            authentication["challenge"].as_str().unwrap_or(""),
+        ));
+    }
+    send_message(&mut socket, OP_IDENTIFY, identify)?;
+
+    let identified = read_message(&mut socket)?;
+    if identified["op"] != OP_IDENTIFIED {
+        return Err(obs_error("OBS rejected the connection"));
+    }
+
+    Ok(socket)
+}
+
+/// Checks that the brightness and temperature to set while live are supported by the device, so
+/// that the command fails straight away rather than when OBS goes live.
+fn check_live_options(device_handle: &DeviceHandle, live_options: &LiveOptions<'_>) -> CliResult {
+    if let Some(brightness_in_lumen) = live_options.brightness_in_lumen {
+        if !device_handle.is_valid_brightness_in_lumen(brightness_in_lumen) {
+            return Err(DeviceError::InvalidBrightness(brightness_in_lumen).into());
+        }
+    }
+    if let Some(temperature_in_kelvin) = live_options.temperature_in_kelvin {
+        if !device_handle.is_valid_temperature_in_kelvin(temperature_in_kelvin) {
+            return Err(DeviceError::InvalidTemperature(temperature_in_kelvin).into());
+        }
+    }
+    Ok(())
+}
+
+fn apply_live_state(
+    device_handle: &DeviceHandle,
+    live_options: &LiveOptions<'_>,
+) -> Result<SavedState, CliError> {
+    let saved_state = SavedState {
+        is_on: device_handle.is_on()?,
+        brightness_in_lumen: device_handle.brightness_in_lumen()?,
+        temperature_in_kelvin: device_handle.temperature_in_kelvin()?,
+    };
+
+    device_handle.set_on(true)?;
+    if let Some(brightness_in_lumen) = live_options.brightness_in_lumen {
+        device_handle.set_brightness_in_lumen(brightness_in_lumen)?;
+    }
+    if let Some(temperature_in_kelvin) = live_options.temperature_in_kelvin {
+        device_handle.set_temperature_in_kelvin(temperature_in_kelvin)?;
+    }
+
+    Ok(saved_state)
+}
+
+fn restore_state(device_handle: &DeviceHandle, saved_state: &SavedState) -> CliResult {
+    device_handle.set_brightness_in_lumen(saved_state.brightness_in_lumen)?;
+    device_handle.set_temperature_in_kelvin(saved_state.temperature_in_kelvin)?;
+    device_handle.set_on(saved_state.is_on)?;
+    Ok(())
+}
+
+/// Restores the device's saved state when dropped, so that it isn't left on if we stop watching
+/// OBS while it's live, for example because the connection to OBS failed or Ctrl+C was pressed.
+struct RestoreGuard<'a> {
+    device_handle: &'a DeviceHandle,
+    saved_state: Option<SavedState>,
+}
+
+impl RestoreGuard<'_> {
+    fn restore(mut self) -> CliResult {
+        match self.saved_state.take() {
+            Some(saved_state) => restore_state(self.device_handle, &saved_state),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RestoreGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(saved_state) = self.saved_state.take() {
+            println!("Stopped watching OBS. Restoring device.");
+            if let Err(error) = restore_state(self.device_handle, &saved_state) {
+                eprintln!("Failed to restore device: {}", error);
+            }
+        }
+    }
+}
+
+pub fn handle_obs_command(
+    options: &config::GlobalOptions,
+    url: &str,
+    password: Option<&str>,
+    live_options: LiveOptions<'_>,
+) -> CliResult {
+    // The device is picked once up front, so that the user isn't asked to pick one every time OBS
+    // goes live and the device which was changed is always the one which is restored
+    let context = config::new_context(options)?;
+    let device_handle = get_first_supported_device(&context, live_options.serial_number)?;
+    check_live_options(&device_handle, &live_options)?;
+
+    let mut socket = connect(url, password)?;
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        stream
+            .set_read_timeout(Some(STOP_CHECK_INTERVAL))
+            .map_err(obs_error)?;
+    }
+    handle_ctrl_c()?;
+    println!("Connected to OBS at {}", url);
+
+    let mut active_outputs = [false; OUTPUTS.len()];
+    for (index, (request_type, _)) in OUTPUTS.iter().enumerate() {
+        send_message(
+            &mut socket,
+            OP_REQUEST,
+            json!({ "requestType": request_type, "requestId": index.to_string() }),
+        )?;
+    }
+
+    let mut restore_guard: Option<RestoreGuard<'_>> = None;
+
+    // The restore guard is dropped when this returns, including when Ctrl+C is pressed
+    while let Some(message) = read_message_until_stopped(&mut socket)? {
+        let data = &message["d"];
+
+        match message["op"].as_u64() {
+            Some(OP_REQUEST_RESPONSE) => {
+                if let Some(index) = OUTPUTS
+                    .iter()
+                    .position(|(request_type, _)| data["requestType"] == *request_type)
+                {
+                    active_outputs[index] = data["responseData"]["outputActive"] == true;
+                }
+            }
+            Some(OP_EVENT) => {
+                if let Some(index) = OUTPUTS
+                    .iter()
+                    .position(|(_, event_type)| data["eventType"] == *event_type)
+                {
+                    active_outputs[index] = data["eventData"]["outputActive"] == true;
+                }
+            }
+            _ => continue,
+        }
+
+        let is_live = active_outputs.iter().any(|active| *active);
+        if is_live && restore_guard.is_none() {
+            println!("OBS is live. Turning on device.");
+            restore_guard = Some(RestoreGuard {
+                device_handle: &device_handle,
+                saved_state: Some(apply_live_state(&device_handle, &live_options)?),
+            });
+        } else if !is_live {
+            if let Some(restore_guard) = restore_guard.take() {
+                println!("OBS is no longer live. Restoring device.");
+                restore_guard.restore()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litra::mock::MockDevice;
+    use litra::DeviceType;
+
+    fn live_options(
+        brightness_in_lumen: Option<u16>,
+        temperature_in_kelvin: Option<u16>,
+    ) -> LiveOptions<'static> {
+        LiveOptions {
+            serial_number: None,
+            brightness_in_lumen,
+            temperature_in_kelvin,
+        }
+    }
+
+    #[test]
+    fn checks_live_options_before_changing_the_device() {
+        let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+        let device_handle = device.open();
+
+        assert!(check_live_options(&device_handle, &live_options(Some(100), Some(5000))).is_ok());
+        assert!(matches!(
+            check_live_options(&device_handle, &live_options(Some(1000), None)),
+            Err(CliError::DeviceError(DeviceError::InvalidBrightness(1000)))
+        ));
+        assert!(matches!(
+            check_live_options(&device_handle, &live_options(None, Some(5050))),
+            Err(CliError::DeviceError(DeviceError::InvalidTemperature(5050)))
+        ));
+        assert!(device.written_reports().is_empty());
+    }
+}
@@ -0,0 +1,333 @@
+//! An Open Sound Control (OSC) server, so that AV tools, TouchOSC layouts and show control
+//! software can control devices using the protocol they already speak.
+//!
+//! Messages are addressed to `/litra/<serial number>/<property>`, where the serial number can be
+//! `*` to address all devices. The properties are:
+//!
+//! - `power`: turns the device on with a non-zero number or `true`, or off with 0 or `false`
+//! - `brightness`: sets the brightness in lumens with an integer, or as a fraction of the device's
+//!   range with a float between 0 and 1, as sent by faders
+//! - `temperature`: sets the temperature in Kelvin with an integer, or as a fraction of the
+//!   device's range with a float between 0 and 1
+
+use crate::{config, explain_open_error, CliError, CliResult};
+use litra::{DeviceHandle, DeviceResult};
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, UdpSocket};
+
+const BUNDLE_ID: &[u8] = b"#bundle\0";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Argument {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+}
+
+impl Argument {
+    fn as_bool(self) -> bool {
+        match self {
+            Argument::Int(value) => value != 0,
+            Argument::Float(value) => value != 0.0,
+            Argument::Bool(value) => value,
+        }
+    }
+}
+
+/// Reads an OSC string, which is null-terminated and padded to a multiple of 4 bytes, returning
+/// it and the rest of the data.
+fn read_string(data: &[u8]) -> Option<(&str, &[u8])> {
+    let length = data.iter().position(|&byte| byte == 0)?;
+    let string = std::str::from_utf8(&data[..length]).ok()?;
+    let padded_length = (length / 4 + 1) * 4;
+    Some((string, data.get(padded_length..)?))
+}
+
+fn read_u32(data: &[u8]) -> Option<(u32, &[u8])> {
+    let bytes = data.get(..4)?.try_into().ok()?;
+    Some((u32::from_be_bytes(bytes), &data[4..]))
+}
+
+/// Parses an OSC packet, which is either a message or a bundle of packets, into messages.
+fn parse_packet<'a>(packet: &'a [u8], messages: &mut Vec<(&'a str, Vec<Argument>)>) -> Option<()> {
+    if let Some(mut elements) = packet.strip_prefix(BUNDLE_ID) {
+        // Bundles have a time tag, which is ignored, followed by sized elements
+        elements = elements.get(8..)?;
+        while !elements.is_empty() {
+            let (size, rest) = read_u32(elements)?;
+            let size = usize::try_from(size).ok()?;
+            parse_packet(rest.get(..size)?, messages)?;
+            elements = &rest[size..];
+        }
+        return Some(());
+    }
+
+    let (address, rest) = read_string(packet)?;
+    let (type_tags, mut rest) = read_string(rest)?;
+    let mut arguments = Vec::new();
+    for type_tag in type_tags.strip_prefix(',')?.chars() {
+        let argument = match type_tag {
+            'i' => {
+                let (value, remaining) = read_u32(rest)?;
+                rest = remaining;
+                Argument::Int(value as i32)
+            }
+            'f' => {
+                let (value, remaining) = read_u32(rest)?;
+                rest = remaining;
+                Argument::Float(f32::from_bits(value))
+            }
+            'T' => Argument::Bool(true),
+            'F' => Argument::Bool(false),
+            _ => return None,
+        };
+        arguments.push(argument);
+    }
+    messages.push((address, arguments));
+    Some(())
+}
+
+/// Splits a message address like `/litra/<serial number>/<property>` into the serial number (or
+/// `*`) and the property.
+fn parse_address(address: &str) -> Option<(&str, &str)> {
+    let mut parts = address.split('/');
+    match (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) {
+        (Some(""), Some("litra"), Some(target), Some(property), None) => Some((target, property)),
+        _ => None,
+    }
+}
+
+/// Converts an argument to a value in the range, treating floats as a fraction of it.
+fn value_in_range(argument: Argument, minimum: u16, maximum: u16) -> Option<u16> {
+    match argument {
+        Argument::Int(value) => u16::try_from(value).ok(),
+        Argument::Float(fraction) => {
+            let fraction = f64::from(fraction).clamp(0.0, 1.0);
+            Some(minimum + (fraction * f64::from(maximum - minimum)).round() as u16)
+        }
+        Argument::Bool(_) => None,
+    }
+}
+
+fn apply(device_handle: &DeviceHandle, property: &str, argument: Argument) -> DeviceResult<bool> {
+    match property {
+        "power" => device_handle.set_on(argument.as_bool())?,
+        "brightness" => {
+            let Some(brightness_in_lumen) = value_in_range(
+                argument,
+                device_handle.minimum_brightness_in_lumen(),
+                device_handle.maximum_brightness_in_lumen(),
+            ) else {
+                return Ok(false);
+            };
+            device_handle.set_brightness_in_lumen(brightness_in_lumen)?;
+        }
+        "temperature" => {
+            let step = device_handle.temperature_step_in_kelvin();
+            let Some(temperature_in_kelvin) = value_in_range(
+                argument,
+                device_handle.minimum_temperature_in_kelvin(),
+                device_handle.maximum_temperature_in_kelvin(),
+            ) else {
+                return Ok(false);
+            };
+            let temperature_in_kelvin = match argument {
+                Argument::Float(_) => (temperature_in_kelvin + step / 2) / step * step,
+                _ => temperature_in_kelvin,
+            };
+            device_handle.set_temperature_in_kelvin(temperature_in_kelvin)?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+pub fn handle_osc_command(options: &config::GlobalOptions, port: u16) -> CliResult {
+    let context = config::new_context(options)?;
+    let mut device_handles = BTreeMap::new();
+    for device in context.get_connected_devices() {
+        let Some(serial_number) = device.serial_number() else {
+            continue;
+        };
+        device_handles.insert(
+            serial_number.to_string(),
+            device.open(&context).map_err(explain_open_error)?,
+        );
+    }
+    if device_handles.is_empty() {
+        return Err(CliError::DeviceNotFound);
+    }
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))
+        .map_err(|error| CliError::OscFailed(error.to_string()))?;
+    println!("Listening for OSC messages on port {} for:", port);
+    for (serial_number, device_handle) in &device_handles {
+        println!(
+            "- {} ({}): /litra/{}/...",
+            device_handle.device_type(),
+            serial_number,
+            serial_number
+        );
+    }
+
+    let mut buffer = [0u8; 4096];
+    loop {
+        let length = socket
+            .recv(&mut buffer)
+            .map_err(|error| CliError::OscFailed(error.to_string()))?;
+        let mut messages = Vec::new();
+        if parse_packet(&buffer[..length], &mut messages).is_none() {
+            eprintln!("Ignoring an invalid OSC packet");
+            continue;
+        }
+
+        for (address, arguments) in messages {
+            let (Some((target, property)), Some(&argument)) =
+                (parse_address(address), arguments.first())
+            else {
+                eprintln!("Ignoring an OSC message to {}", address);
+                continue;
+            };
+
+            for (serial_number, device_handle) in &device_handles {
+                if target != "*" && target != serial_number {
+                    continue;
+                }
+                match apply(device_handle, property, argument) {
+                    Ok(true) => {}
+                    Ok(false) => eprintln!("Ignoring an OSC message to {}", address),
+                    Err(error) if options.strict => return Err(error.into()),
+                    Err(error) => eprintln!("Failed to update {}: {}", serial_number, error),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A message to `/litra/*/power` with an integer argument of 1.
+    const POWER_MESSAGE: [u8; 24] = [
+        b'/', b'l', b'i', b't', b'r', b'a', b'/', b'*', b'/', b'p', b'o', b'w', b'e', b'r', 0, 0,
+        b',', b'i', 0, 0, // Type tags
+        0x00, 0x00, 0x00, 0x01,
+    ];
+
+    /// A message to `/litra/AB/brightness` with a float argument of 0.5.
+    const BRIGHTNESS_MESSAGE: [u8; 32] = [
+        b'/', b'l', b'i', b't', b'r', b'a', b'/', b'A', b'B', b'/', b'b', b'r', b'i', b'g', b'h',
+        b't', b'n', b'e', b's', b's', 0, 0, 0, 0, // Padded to a multiple of 4 bytes
+        b',', b'f', 0, 0, // Type tags
+        0x3f, 0x00, 0x00, 0x00,
+    ];
+
+    fn parse(packet: &[u8]) -> Option<Vec<(&str, Vec<Argument>)>> {
+        let mut messages = Vec::new();
+        parse_packet(packet, &mut messages)?;
+        Some(messages)
+    }
+
+    #[test]
+    fn reads_padded_strings() {
+        assert_eq!(read_string(b"abc\0rest"), Some(("abc", &b"rest"[..])));
+        assert_eq!(
+            read_string(b"abcd\0\0\0\0rest"),
+            Some(("abcd", &b"rest"[..]))
+        );
+        assert_eq!(read_string(b"abcd"), None);
+        assert_eq!(read_string(b"abcd\0\0"), None);
+    }
+
+    #[test]
+    fn parses_messages() {
+        assert_eq!(
+            parse(&POWER_MESSAGE),
+            Some(vec![("/litra/*/power", vec![Argument::Int(1)])])
+        );
+        assert_eq!(
+            parse(&BRIGHTNESS_MESSAGE),
+            Some(vec![("/litra/AB/brightness", vec![Argument::Float(0.5)])])
+        );
+    }
+
+    #[test]
+    fn parses_boolean_arguments_without_data() {
+        let packet = b"/litra/*/power\0\0,TF\0";
+        assert_eq!(
+            parse(packet),
+            Some(vec![(
+                "/litra/*/power",
+                vec![Argument::Bool(true), Argument::Bool(false)]
+            )])
+        );
+    }
+
+    #[test]
+    fn parses_bundles() {
+        let mut packet = BUNDLE_ID.to_vec();
+        packet.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01]); // Time tag
+        for message in [&POWER_MESSAGE[..], &BRIGHTNESS_MESSAGE[..]] {
+            packet.extend_from_slice(&u32::try_from(message.len()).unwrap().to_be_bytes());
+            packet.extend_from_slice(message);
+        }
+
+        assert_eq!(
+            parse(&packet),
+            Some(vec![
+                ("/litra/*/power", vec![Argument::Int(1)]),
+                ("/litra/AB/brightness", vec![Argument::Float(0.5)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_packets() {
+        // Truncated argument
+        assert_eq!(parse(&POWER_MESSAGE[..22]), None);
+        // Missing the `,` before the type tags
+        let mut packet = POWER_MESSAGE;
+        packet[16] = b'i';
+        packet[17] = 0;
+        assert_eq!(parse(&packet), None);
+        // Unsupported argument type
+        assert_eq!(parse(b"/litra/*/power\0\0,s\0\0on\0\0"), None);
+        // Bundle element longer than the bundle
+        let mut packet = BUNDLE_ID.to_vec();
+        packet.extend_from_slice(&[0x00; 8]);
+        packet.extend_from_slice(&[0x00, 0x00, 0x00, 0x40]);
+        packet.extend_from_slice(&POWER_MESSAGE);
+        assert_eq!(parse(&packet), None);
+    }
+
+    #[test]
+    fn parses_addresses() {
+        assert_eq!(parse_address("/litra/*/power"), Some(("*", "power")));
+        assert_eq!(
+            parse_address("/litra/2325FE7044P8/brightness"),
+            Some(("2325FE7044P8", "brightness"))
+        );
+        assert_eq!(parse_address("/litra/*"), None);
+        assert_eq!(parse_address("/litra/*/power/extra"), None);
+        assert_eq!(parse_address("/other/*/power"), None);
+        assert_eq!(parse_address("litra/*/power"), None);
+    }
+
+    #[test]
+    fn converts_arguments_to_values_in_range() {
+        assert_eq!(value_in_range(Argument::Int(100), 20, 250), Some(100));
+        assert_eq!(value_in_range(Argument::Int(-1), 20, 250), None);
+        assert_eq!(value_in_range(Argument::Float(0.0), 20, 250), Some(20));
+        assert_eq!(value_in_range(Argument::Float(0.5), 20, 250), Some(135));
+        assert_eq!(value_in_range(Argument::Float(1.0), 20, 250), Some(250));
+        assert_eq!(value_in_range(Argument::Float(2.0), 20, 250), Some(250));
+        assert_eq!(value_in_range(Argument::Bool(true), 20, 250), None);
+    }
+}
@@ -0,0 +1,179 @@
+//! Pins a device to a state for a while, putting it back whenever it's changed with the device's
+//! buttons or by other software, so that an accidental nudge during a recording doesn't go
+//! unnoticed.
+
+use crate::{config, get_first_supported_device, percentage_within_range, CliResult};
+use litra::{DeviceHandle, DeviceResult};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// A brightness given either in lumens, or as a percentage of the device's range.
+#[derive(Debug, Clone, Copy)]
+pub enum Brightness {
+    Lumen(u16),
+    Percentage(u8),
+}
+
+/// Parses a brightness like `250` (in lumens) or `80%`.
+pub fn parse_brightness(value: &str) -> Result<Brightness, String> {
+    match value.strip_suffix('%') {
+        Some(percentage) => match percentage.trim().parse() {
+            Ok(percentage) if percentage <= 100 => Ok(Brightness::Percentage(percentage)),
+            _ => Err("Expected a percentage between 0% and 100%".to_string()),
+        },
+        None => value
+            .trim_end_matches("lm")
+            .trim()
+            .parse()
+            .map(Brightness::Lumen)
+            .map_err(|_| {
+                "Expected a brightness in lumens, e.g. `250`, or a percentage, e.g. `80%`"
+                    .to_string()
+            }),
+    }
+}
+
+#[derive(Debug)]
+struct PinnedState {
+    brightness_in_lumen: Option<u16>,
+    /// The brightness the device reports once `brightness_in_lumen` has been set, which differs
+    /// on models which round brightness to internal steps.
+    reported_brightness_in_lumen: Option<u16>,
+    temperature_in_kelvin: Option<u16>,
+}
+
+impl PinnedState {
+    fn new(brightness_in_lumen: Option<u16>, temperature_in_kelvin: Option<u16>) -> Self {
+        PinnedState {
+            brightness_in_lumen,
+            reported_brightness_in_lumen: brightness_in_lumen,
+            temperature_in_kelvin,
+        }
+    }
+
+    /// Applies the state for the first time, then remembers the brightness the device reports,
+    /// so that rounding isn't mistaken for a change.
+    fn apply(&mut self, device_handle: &DeviceHandle) -> DeviceResult<()> {
+        self.enforce(device_handle)?;
+        if self.brightness_in_lumen.is_some() {
+            self.reported_brightness_in_lumen = Some(device_handle.brightness_in_lumen()?);
+        }
+        Ok(())
+    }
+
+    /// Checks the device's state, putting back anything which has changed. Returns a description
+    /// of each change which was undone.
+    fn enforce(&self, device_handle: &DeviceHandle) -> DeviceResult<Vec<String>> {
+        let mut restored = Vec::new();
+
+        if let (Some(brightness_in_lumen), Some(reported_brightness_in_lumen)) =
+            (self.brightness_in_lumen, self.reported_brightness_in_lumen)
+        {
+            let current_brightness_in_lumen = device_handle.brightness_in_lumen()?;
+            if current_brightness_in_lumen != reported_brightness_in_lumen {
+                device_handle.set_brightness_in_lumen(brightness_in_lumen)?;
+                restored.push(format!(
+                    "brightness from {} lm to {} lm",
+                    current_brightness_in_lumen, reported_brightness_in_lumen
+                ));
+            }
+        }
+        if let Some(temperature_in_kelvin) = self.temperature_in_kelvin {
+            let current_temperature_in_kelvin = device_handle.temperature_in_kelvin()?;
+            if current_temperature_in_kelvin != temperature_in_kelvin {
+                device_handle.set_temperature_in_kelvin(temperature_in_kelvin)?;
+                restored.push(format!(
+                    "temperature from {} K to {} K",
+                    current_temperature_in_kelvin, temperature_in_kelvin
+                ));
+            }
+        }
+        if !device_handle.is_on()? {
+            device_handle.set_on(true)?;
+            restored.push("power from off to on".to_string());
+        }
+
+        Ok(restored)
+    }
+}
+
+pub fn handle_pin_command(
+    options: &config::GlobalOptions,
+    serial_number: Option<&str>,
+    brightness: Option<Brightness>,
+    temperature_in_kelvin: Option<u16>,
+    duration: Option<Duration>,
+    poll_interval: Duration,
+) -> CliResult {
+    let context = config::new_context(options)?;
+    let device_handle = get_first_supported_device(&context, serial_number)?;
+
+    let brightness_in_lumen = brightness.map(|brightness| match brightness {
+        Brightness::Lumen(brightness_in_lumen) => brightness_in_lumen,
+        Brightness::Percentage(percentage) => percentage_within_range(
+            percentage.into(),
+            device_handle.minimum_brightness_in_lumen().into(),
+            device_handle.maximum_brightness_in_lumen().into(),
+        ) as u16,
+    });
+    let mut pinned_state = PinnedState::new(brightness_in_lumen, temperature_in_kelvin);
+
+    // Applying the state first means any invalid values are reported straight away
+    pinned_state.apply(&device_handle)?;
+    match duration {
+        Some(duration) => println!(
+            "Pinned {} for {}. Press Ctrl+C to stop early.",
+            device_handle.device_type(),
+            humantime::format_duration(duration)
+        ),
+        None => println!(
+            "Pinned {}. Press Ctrl+C to stop.",
+            device_handle.device_type()
+        ),
+    }
+
+    let started_at = Instant::now();
+    while duration.is_none_or(|duration| started_at.elapsed() < duration) {
+        thread::sleep(poll_interval);
+        // Failures are reported without giving up, since the device may only be briefly busy
+        match pinned_state.enforce(&device_handle) {
+            Ok(restored) if restored.is_empty() => {}
+            Ok(restored) => println!(
+                "{}: Restored {}",
+                humantime::format_rfc3339_seconds(SystemTime::now()),
+                restored.join(", ")
+            ),
+            Err(error) => eprintln!("Failed to check {}: {}", device_handle.device_type(), error),
+        }
+    }
+
+    println!("Unpinned {}", device_handle.device_type());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litra::mock::MockDevice;
+    use litra::DeviceType;
+
+    #[test]
+    fn doesnt_restore_brightness_rounded_by_the_device() {
+        let device = MockDevice::new(DeviceType::LitraBeam, "2325FE7044P8");
+        device.set_brightness_rounding_in_lumen(50);
+        let device_handle = device.open();
+
+        let mut pinned_state = PinnedState::new(Some(150), Some(4000));
+        pinned_state.apply(&device_handle).unwrap();
+        assert_eq!(device.brightness_in_lumen(), 130);
+        assert!(pinned_state.enforce(&device_handle).unwrap().is_empty());
+
+        device.set_brightness_in_lumen(300);
+        assert_eq!(
+            pinned_state.enforce(&device_handle).unwrap(),
+            ["brightness from 300 lm to 130 lm"]
+        );
+        assert_eq!(device.brightness_in_lumen(), 130);
+        assert!(pinned_state.enforce(&device_handle).unwrap().is_empty());
+    }
+}
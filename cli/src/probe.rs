@@ -0,0 +1,177 @@
+//! Probes a Logitech HID device with an unrecognized product ID, sending the HID++ queries used
+//! for supported devices and reporting which respond, to make it easier to add support for new
+//! models.
+//!
+//! Only read-only queries are sent: the illumination queries are only sent to the feature index
+//! the device reports for the illumination feature, since the same function numbers could do
+//! something else in another feature. The device is unknown though, so the user is asked to
+//! confirm first.
+
+use crate::{confirm, CliError, CliResult};
+use litra::{
+    DeviceDescriptor, DeviceError, DeviceHandle, DeviceType, Litra, RawResponse, PRODUCT_IDS,
+    VENDOR_ID,
+};
+use std::ffi::CString;
+
+const ROOT_FEATURE_INDEX: u8 = 0x00;
+const GET_FEATURE_FUNCTION: u8 = 0x00;
+const ILLUMINATION_FEATURE_ID: u16 = 0x1990;
+
+/// The HID++ features we look for, by feature ID.
+const FEATURES: [(u16, &str); 4] = [
+    (0x0001, "Feature set"),
+    (0x0003, "Device information"),
+    (0x0005, "Device name"),
+    (ILLUMINATION_FEATURE_ID, "Illumination"),
+];
+
+/// The illumination queries sent by the library, by function number.
+const ILLUMINATION_QUERIES: [(u8, &str); 3] = [
+    (0x00, "Get on/off"),
+    (0x03, "Get brightness"),
+    (0x08, "Get temperature"),
+];
+
+fn format_response(response: &Result<RawResponse, DeviceError>) -> String {
+    match response {
+        Ok(response) => match response.error_code() {
+            Some(error_code) => format!("error 0x{:02x}", error_code),
+            None => response
+                .payload()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<Vec<_>>()
+                .join(" "),
+        },
+        Err(DeviceError::Timeout | DeviceError::Unsupported) => "no response".to_string(),
+        Err(error) => format!("failed: {}", error),
+    }
+}
+
+/// Sends the probe's queries to a device, returning a line describing each response.
+fn probe(device_handle: &DeviceHandle) -> Vec<String> {
+    let mut lines = vec!["Features:".to_string()];
+    let mut illumination_feature_index = None;
+    for (feature_id, name) in FEATURES {
+        let response = device_handle.send_raw(
+            ROOT_FEATURE_INDEX,
+            GET_FEATURE_FUNCTION,
+            &feature_id.to_be_bytes(),
+        );
+        let result = match &response {
+            Ok(response) if response.error_code().is_none() => match response.payload()[0] {
+                0x00 => "not present".to_string(),
+                feature_index => {
+                    if feature_id == ILLUMINATION_FEATURE_ID {
+                        illumination_feature_index = Some(feature_index);
+                    }
+                    format!("index 0x{:02x}", feature_index)
+                }
+            },
+            response => format_response(response),
+        };
+        lines.push(format!("  - {} (0x{:04x}): {}", name, feature_id, result));
+    }
+
+    match illumination_feature_index {
+        Some(feature_index) => {
+            lines.push(format!(
+                "Illumination queries at index 0x{:02x}:",
+                feature_index
+            ));
+            for (function, name) in ILLUMINATION_QUERIES {
+                let response = device_handle.send_raw(feature_index, function, &[]);
+                lines.push(format!("  - {}: {}", name, format_response(&response)));
+            }
+        }
+        None => lines.push(
+            "Illumination queries: skipped, since the illumination feature wasn't found"
+                .to_string(),
+        ),
+    }
+
+    lines
+}
+
+pub fn handle_probe_command(device_path: &str, yes: bool) -> CliResult {
+    let context = Litra::new()?;
+    let device_info = context
+        .hidapi()
+        .device_list()
+        .find(|device_info| device_info.path().to_str() == Ok(device_path))
+        .ok_or(CliError::DeviceNotFound)?;
+
+    println!(
+        "Device: {} (vendor ID 0x{:04x}, product ID 0x{:04x}, usage page 0x{:04x})",
+        device_info.product_string().unwrap_or("Unknown"),
+        device_info.vendor_id(),
+        device_info.product_id(),
+        device_info.usage_page()
+    );
+
+    if device_info.vendor_id() != VENDOR_ID {
+        return Err(CliError::ProbeFailed(
+            "only Logitech devices can be probed".to_string(),
+        ));
+    }
+    if let Some((_, device_type)) = PRODUCT_IDS
+        .iter()
+        .find(|(product_id, _)| *product_id == device_info.product_id())
+    {
+        println!("This device is already supported as a {}.", device_type);
+    }
+
+    if !yes
+        && !confirm(
+            "This will send read-only HID++ queries to a device which may not be a Litra light. Continue?",
+        )?
+    {
+        return Ok(());
+    }
+
+    let path =
+        CString::new(device_path).map_err(|error| CliError::ProbeFailed(error.to_string()))?;
+    let hid_device = context
+        .hidapi()
+        .open_path(&path)
+        .map_err(|error| CliError::DeviceError(error.into()))?;
+    // The probe only sends raw requests, which don't use the descriptor's feature index or ranges
+    let descriptor = DeviceDescriptor {
+        product_id: device_info.product_id(),
+        ..DeviceDescriptor::for_device_type(DeviceType::LitraGlow)
+    };
+    let device_handle = DeviceHandle::from_transport(hid_device, descriptor);
+
+    for line in probe(&device_handle) {
+        println!("{}", line);
+    }
+
+    println!();
+    println!(
+        "To help add support for this device, please open an issue at https://github.com/timrogers/litra-rs/issues including the output above."
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litra::mock::MockDevice;
+
+    #[test]
+    fn reports_the_features_a_device_responds_to() {
+        let device_handle = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8").open();
+        let lines = probe(&device_handle);
+
+        assert_eq!(lines[0], "Features:");
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("  - Device information (0x0003): index 0x")));
+        assert!(lines.contains(&"  - Device name (0x0005): not present".to_string()));
+        assert_eq!(
+            lines.last().unwrap(),
+            "Illumination queries: skipped, since the illumination feature wasn't found"
+        );
+    }
+}
@@ -0,0 +1,635 @@
+//! A long-lived machine interface speaking JSON-RPC 2.0 over standard input and output, intended
+//! for Stream Deck plugins and other integrations which want low-latency control and live state
+//! updates without spawning a new process for every command.
+//!
+//! Each message is a single line of JSON. See the "Machine interface" section of the README for
+//! the documented protocol.
+
+use crate::{
+    check_serial_number_if_some, config, get_device_info, percentage_within_range,
+    read_device_info, CliError, CliResult, DeviceInfo,
+};
+use litra::{DeviceError, DeviceHandle, Litra};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The version of the protocol. This is bumped whenever a backwards-incompatible change is made.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const DEVICE_ERROR: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+    /// Whether the error suggests that the device has been disconnected, which leaves its cached
+    /// handle unusable even once it's plugged back in.
+    is_disconnected: bool,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            is_disconnected: false,
+        }
+    }
+}
+
+impl From<CliError> for RpcError {
+    fn from(error: CliError) -> Self {
+        match error {
+            CliError::DeviceError(error) => error.into(),
+            error => RpcError::new(DEVICE_ERROR, error.to_string()),
+        }
+    }
+}
+
+impl From<DeviceError> for RpcError {
+    fn from(error: DeviceError) -> Self {
+        RpcError {
+            is_disconnected: matches!(error, DeviceError::HidError(_) | DeviceError::NotConnected),
+            ..RpcError::new(DEVICE_ERROR, error.to_string())
+        }
+    }
+}
+
+/// An opened device, along with its serial number (or an empty string if it doesn't have one).
+struct OpenDevice {
+    serial_number: String,
+    device_handle: DeviceHandle,
+}
+
+/// Devices are keyed by their path rather than their serial number, since devices without a
+/// serial number would otherwise be mixed up.
+struct RpcServer {
+    context: Litra,
+    devices: HashMap<CString, OpenDevice>,
+    subscribed: bool,
+    last_states: HashMap<CString, DeviceInfo>,
+}
+
+impl RpcServer {
+    fn new(options: &config::GlobalOptions) -> Result<Self, CliError> {
+        Ok(RpcServer {
+            context: config::new_context(options)?,
+            devices: HashMap::new(),
+            subscribed: false,
+            last_states: HashMap::new(),
+        })
+    }
+
+    /// Handles a line of input, returning the response to write, if any. Notifications (requests
+    /// without an `id`) don't get a response.
+    fn handle_line(&mut self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(error) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, error.to_string()),
+                ))
+            }
+        };
+
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "Missing `method`"),
+            ));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = self.dispatch(method, &params);
+
+        id.map(|id| response(id, result))
+    }
+
+    fn dispatch(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "version" => Ok(version()),
+            "list" => self.list(),
+            "get" => self.get(params),
+            "set" => self.set(params),
+            "toggle" => self.toggle(params),
+            "subscribe" => {
+                self.refresh()?;
+                self.subscribed = true;
+                self.last_states = self.current_states();
+                Ok(serialize(&sorted_states(&self.last_states)))
+            }
+            "unsubscribe" => {
+                self.subscribed = false;
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method `{}`", method),
+            )),
+        }
+    }
+
+    fn list(&mut self) -> Result<Value, RpcError> {
+        self.refresh()?;
+        Ok(serialize(&sorted_states(&self.current_states())))
+    }
+
+    fn set(&mut self, params: &Value) -> Result<Value, RpcError> {
+        let on = optional_param(params, "on", Value::as_bool)?;
+        let brightness_in_lumen = optional_u16_param(params, "brightness_in_lumen")?;
+        let brightness_percentage = optional_u16_param(params, "brightness_percentage")?;
+        let temperature_in_kelvin = optional_u16_param(params, "temperature_in_kelvin")?;
+
+        if brightness_in_lumen.is_some() && brightness_percentage.is_some() {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                "Only one of `brightness_in_lumen` and `brightness_percentage` can be set",
+            ));
+        }
+
+        if brightness_percentage.is_some_and(|percentage| percentage > 100) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                "`brightness_percentage` must be between 0 and 100",
+            ));
+        }
+
+        self.with_device(params, |device| {
+            set_state(
+                device,
+                on,
+                brightness_in_lumen,
+                brightness_percentage,
+                temperature_in_kelvin,
+            )
+        })
+    }
+
+    fn get(&mut self, params: &Value) -> Result<Value, RpcError> {
+        self.with_device(params, state_of)
+    }
+
+    fn toggle(&mut self, params: &Value) -> Result<Value, RpcError> {
+        self.with_device(params, |device| {
+            device
+                .device_handle
+                .set_on(!device.device_handle.is_on()?)?;
+            state_of(device)
+        })
+    }
+
+    /// Runs an operation on the device targeted by the `serial_number` parameter. If the device's
+    /// cached handle fails because the device was disconnected, e.g. when it has been unplugged and
+    /// plugged back in, the device is opened again and the operation is retried once.
+    fn with_device(
+        &mut self,
+        params: &Value,
+        operation: impl Fn(&OpenDevice) -> Result<Value, RpcError>,
+    ) -> Result<Value, RpcError> {
+        let (path, was_cached) = self.device(params)?;
+        match operation(&self.devices[&path]) {
+            Err(error) if was_cached && error.is_disconnected => {
+                self.devices.remove(&path);
+                self.context.refresh_connected_devices()?;
+                let (path, _) = self.device(params)?;
+                operation(&self.devices[&path])
+            }
+            result => result,
+        }
+    }
+
+    /// Finds the device targeted by the `serial_number` parameter, or the first connected device
+    /// if no serial number is given, opening it if needed. Returns the device's path, and whether
+    /// an existing handle was used.
+    fn device(&mut self, params: &Value) -> Result<(CString, bool), RpcError> {
+        let requested_serial_number = optional_param(params, "serial_number", Value::as_str)?;
+
+        let cached_path = requested_serial_number.and_then(|requested_serial_number| {
+            self.devices
+                .iter()
+                .find(|(_, device)| device.serial_number == requested_serial_number)
+                .map(|(path, _)| path.clone())
+        });
+        let (path, was_cached) = match cached_path {
+            Some(path) => (path, true),
+            None => {
+                if !self
+                    .context
+                    .get_connected_devices()
+                    .any(|device| check_serial_number_if_some(requested_serial_number)(&device))
+                {
+                    self.context.refresh_connected_devices()?;
+                }
+                let device = self
+                    .context
+                    .get_connected_devices()
+                    .find(check_serial_number_if_some(requested_serial_number))
+                    .ok_or(CliError::DeviceNotFound)?;
+                let path = device.path().to_owned();
+                let was_cached = self.devices.contains_key(&path);
+                if !was_cached {
+                    let open_device = OpenDevice {
+                        serial_number: device.serial_number().unwrap_or("").to_string(),
+                        device_handle: device.open(&self.context)?,
+                    };
+                    self.devices.insert(path.clone(), open_device);
+                }
+                (path, was_cached)
+            }
+        };
+
+        Ok((path, was_cached))
+    }
+
+    /// Refreshes the list of connected devices, dropping cached handles for devices which have
+    /// been disconnected and opening handles for new ones.
+    fn refresh(&mut self) -> Result<(), RpcError> {
+        self.context.refresh_connected_devices()?;
+
+        let mut connected = HashMap::new();
+        for device in self.context.get_connected_devices() {
+            let path = device.path().to_owned();
+            match self.devices.remove(&path) {
+                Some(open_device) => {
+                    connected.insert(path, open_device);
+                }
+                None => {
+                    if let Ok(device_handle) = device.open(&self.context) {
+                        let serial_number = device.serial_number().unwrap_or("").to_string();
+                        connected.insert(
+                            path,
+                            OpenDevice {
+                                serial_number,
+                                device_handle,
+                            },
+                        );
+                    }
+                }
+            }
+        }
+        self.devices = connected;
+
+        Ok(())
+    }
+
+    fn current_states(&self) -> HashMap<CString, DeviceInfo> {
+        self.devices
+            .iter()
+            .filter_map(|(path, device)| {
+                get_device_info(&device.serial_number, &device.device_handle)
+                    .map(|state| (path.clone(), state))
+            })
+            .collect()
+    }
+
+    /// Polls all devices, returning notifications for any which have changed state, been
+    /// connected or been disconnected since the last poll.
+    fn poll(&mut self) -> Vec<Value> {
+        if self.refresh().is_err() {
+            return Vec::new();
+        }
+
+        let states = self.current_states();
+        let mut notifications = Vec::new();
+
+        for (path, state) in &states {
+            if self.last_states.get(path) != Some(state) {
+                notifications.push(notification("device_state_changed", serialize(state)));
+            }
+        }
+        for (path, last_state) in &self.last_states {
+            if !states.contains_key(path) {
+                notifications.push(notification(
+                    "device_disconnected",
+                    json!({ "serial_number": last_state.serial_number }),
+                ));
+            }
+        }
+
+        self.last_states = states;
+        notifications
+    }
+}
+
+fn version() -> Value {
+    json!({
+        "protocol_version": PROTOCOL_VERSION,
+        "litra_version": env!("CARGO_PKG_VERSION"),
+    })
+}
+
+fn sorted_states(states: &HashMap<CString, DeviceInfo>) -> Vec<&DeviceInfo> {
+    let mut states: Vec<&DeviceInfo> = states.values().collect();
+    states.sort_by(|a, b| a.serial_number.cmp(&b.serial_number));
+    states
+}
+
+fn serialize<T: serde::Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+/// Applies a `set` request to a device, checking everything before anything is written so that an
+/// invalid request doesn't leave the device half-updated.
+fn set_state(
+    device: &OpenDevice,
+    on: Option<bool>,
+    brightness_in_lumen: Option<u16>,
+    brightness_percentage: Option<u16>,
+    temperature_in_kelvin: Option<u16>,
+) -> Result<Value, RpcError> {
+    let device_handle = &device.device_handle;
+
+    let brightness_in_lumen = brightness_in_lumen.or_else(|| {
+        brightness_percentage.map(|percentage| {
+            percentage_within_range(
+                percentage.into(),
+                device_handle.minimum_brightness_in_lumen().into(),
+                device_handle.maximum_brightness_in_lumen().into(),
+            ) as u16
+        })
+    });
+
+    if let Some(brightness_in_lumen) = brightness_in_lumen {
+        if !device_handle.is_valid_brightness_in_lumen(brightness_in_lumen) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!(
+                    "`brightness_in_lumen` must be between {} and {}",
+                    device_handle.minimum_brightness_in_lumen(),
+                    device_handle.maximum_brightness_in_lumen()
+                ),
+            ));
+        }
+    }
+    if let Some(temperature_in_kelvin) = temperature_in_kelvin {
+        if !device_handle.is_valid_temperature_in_kelvin(temperature_in_kelvin) {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!(
+                    "`temperature_in_kelvin` must be a multiple of {} between {} and {}",
+                    device_handle.temperature_step_in_kelvin(),
+                    device_handle.minimum_temperature_in_kelvin(),
+                    device_handle.maximum_temperature_in_kelvin()
+                ),
+            ));
+        }
+    }
+
+    if let Some(on) = on {
+        device_handle.set_on(on)?;
+    }
+    if let Some(brightness_in_lumen) = brightness_in_lumen {
+        device_handle.set_brightness_in_lumen(brightness_in_lumen)?;
+    }
+    if let Some(temperature_in_kelvin) = temperature_in_kelvin {
+        device_handle.set_temperature_in_kelvin(temperature_in_kelvin)?;
+    }
+
+    state_of(device)
+}
+
+fn state_of(device: &OpenDevice) -> Result<Value, RpcError> {
+    let state = read_device_info(&device.serial_number, &device.device_handle)?;
+    Ok(serialize(&state))
+}
+
+fn optional_param<'a, T>(
+    params: &'a Value,
+    name: &str,
+    convert: impl Fn(&'a Value) -> Option<T>,
+) -> Result<Option<T>, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => convert(value)
+            .map(Some)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Invalid `{}`", name))),
+    }
+}
+
+fn optional_u16_param(params: &Value, name: &str) -> Result<Option<u16>, RpcError> {
+    optional_param(params, name, |value| {
+        value.as_u64().and_then(|value| u16::try_from(value).ok())
+    })
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_response(id, error),
+    }
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn write_message(message: &Value) -> CliResult {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", message).map_err(CliError::StdioFailed)?;
+    stdout.flush().map_err(CliError::StdioFailed)
+}
+
+pub fn handle_rpc_command(options: &config::GlobalOptions, poll_interval: Duration) -> CliResult {
+    let mut server = RpcServer::new(options)?;
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut last_poll = Instant::now();
+
+    loop {
+        let timeout = poll_interval.saturating_sub(last_poll.elapsed());
+        match receiver.recv_timeout(timeout) {
+            Ok(Ok(line)) => {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if let Some(response) = server.handle_line(&line) {
+                    write_message(&response)?;
+                }
+            }
+            Ok(Err(error)) => return Err(CliError::StdioFailed(error)),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        if last_poll.elapsed() >= poll_interval {
+            last_poll = Instant::now();
+            if server.subscribed {
+                for notification in server.poll() {
+                    write_message(&notification)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{output_schema, Output};
+    use litra::mock::MockDevice;
+    use litra::DeviceType;
+
+    fn assert_valid(message: &Value) {
+        let validator = jsonschema::validator_for(&output_schema(Output::Rpc)).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(message)
+            .map(|error| error.to_string())
+            .collect();
+        assert!(errors.is_empty(), "{} is invalid: {:?}", message, errors);
+    }
+
+    fn device_info() -> DeviceInfo {
+        DeviceInfo {
+            serial_number: "2325FE7044P8".to_string(),
+            device_type: "Litra Beam".to_string(),
+            is_on: false,
+            brightness_in_lumen: 30,
+            temperature_in_kelvin: 2700,
+            minimum_brightness_in_lumen: 30,
+            maximum_brightness_in_lumen: 400,
+            minimum_temperature_in_kelvin: 2700,
+            maximum_temperature_in_kelvin: 6500,
+            firmware_version: None,
+        }
+    }
+
+    #[test]
+    fn requests_match_the_schema() {
+        assert_valid(&json!({ "jsonrpc": "2.0", "id": 1, "method": "version" }));
+        assert_valid(&json!({
+            "jsonrpc": "2.0",
+            "id": "set",
+            "method": "set",
+            "params": {
+                "serial_number": "2325FE7044P8",
+                "on": true,
+                "brightness_percentage": 50,
+                "temperature_in_kelvin": 4000
+            }
+        }));
+        assert_valid(&json!({ "jsonrpc": "2.0", "method": "subscribe" }));
+        assert!(!jsonschema::is_valid(
+            &output_schema(Output::Rpc),
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "set",
+                "params": { "brightness_percentage": 101 }
+            })
+        ));
+    }
+
+    #[test]
+    fn responses_match_the_schema() {
+        let mut states = HashMap::new();
+        states.insert(CString::new("/dev/hidraw0").unwrap(), device_info());
+
+        for result in [
+            version(),
+            serialize(&device_info()),
+            serialize(&sorted_states(&states)),
+            Value::Null,
+        ] {
+            assert_valid(&response(json!(1), Ok(result)));
+        }
+        assert_valid(&response(
+            json!("request"),
+            Err(RpcError::new(INVALID_PARAMS, "Invalid `on`")),
+        ));
+        assert_valid(&error_response(
+            Value::Null,
+            RpcError::new(PARSE_ERROR, "expected value"),
+        ));
+    }
+
+    #[test]
+    fn notifications_match_the_schema() {
+        assert_valid(&notification(
+            "device_state_changed",
+            serialize(&device_info()),
+        ));
+        assert_valid(&notification(
+            "device_disconnected",
+            json!({ "serial_number": "2325FE7044P8" }),
+        ));
+    }
+
+    fn server_with_simulated_devices(simulated_devices: Vec<MockDevice>) -> RpcServer {
+        RpcServer::new(&config::GlobalOptions {
+            timeout: Duration::from_secs(1),
+            retries: 0,
+            strict: false,
+            simulated_devices,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn reopens_devices_which_have_been_reconnected() {
+        let device = MockDevice::new(DeviceType::LitraBeam, "2325FE7044P8");
+        let mut server = server_with_simulated_devices(vec![device.clone()]);
+        let params = json!({ "serial_number": "2325FE7044P8" });
+        assert!(server.dispatch("get", &params).is_ok());
+
+        // Unplugged and plugged back in between calls, leaving the cached handle stale
+        device.disconnect();
+        device.reconnect();
+        let state = server
+            .dispatch(
+                "set",
+                &json!({ "serial_number": "2325FE7044P8", "on": true }),
+            )
+            .ok()
+            .unwrap();
+        assert_eq!(state["is_on"], true);
+        assert!(device.is_on());
+
+        // Called while unplugged, then plugged back in
+        device.disconnect();
+        assert!(server.dispatch("toggle", &params).is_err());
+        device.reconnect();
+        let state = server.dispatch("toggle", &params).ok().unwrap();
+        assert_eq!(state["is_on"], false);
+        assert!(!device.is_on());
+    }
+
+    #[test]
+    fn doesnt_retry_invalid_requests() {
+        let device = MockDevice::new(DeviceType::LitraBeam, "2325FE7044P8");
+        let mut server = server_with_simulated_devices(vec![device.clone()]);
+        let error = server
+            .dispatch(
+                "set",
+                &json!({ "serial_number": "2325FE7044P8", "temperature_in_kelvin": 1000 }),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(error.code, INVALID_PARAMS);
+        assert!(device.written_reports().is_empty());
+    }
+}
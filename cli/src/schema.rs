@@ -0,0 +1,399 @@
+//! JSON Schemas for the machine-readable output of the command line interface, so that
+//! integrations can validate and generate code against it. The schemas are versioned with the
+//! crate, and any backwards-incompatible change to the output is a breaking change.
+
+use crate::{rpc, CliError, CliResult};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Output {
+    /// The output of `litra devices --json`, with or without `--all`
+    Devices,
+    /// Each line of the output of `litra devices --json --poll`
+    DevicesPoll,
+    /// The messages sent and received by `litra rpc`
+    Rpc,
+}
+
+impl Output {
+    fn name(self) -> &'static str {
+        match self {
+            Output::Devices => "devices",
+            Output::DevicesPoll => "devices-poll",
+            Output::Rpc => "rpc",
+        }
+    }
+}
+
+fn device_info_schema() -> Value {
+    let integer = |description: &str| {
+        json!({
+            "description": description,
+            "type": "integer",
+            "minimum": 0,
+            "maximum": 65535
+        })
+    };
+
+    json!({
+        "description": "The state of a device.",
+        "type": "object",
+        "required": [
+            "serial_number",
+            "device_type",
+            "is_on",
+            "brightness_in_lumen",
+            "temperature_in_kelvin",
+            "minimum_brightness_in_lumen",
+            "maximum_brightness_in_lumen",
+            "minimum_temperature_in_kelvin",
+            "maximum_temperature_in_kelvin",
+            "firmware_version"
+        ],
+        "properties": {
+            "serial_number": {
+                "description": "The serial number of the device, or an empty string if it doesn't have one.",
+                "type": "string"
+            },
+            "device_type": {
+                "description": "The model of the device.",
+                "enum": ["Litra Glow", "Litra Beam", "Litra Beam LX"]
+            },
+            "is_on": {
+                "description": "Whether the device is on.",
+                "type": "boolean"
+            },
+            "brightness_in_lumen": integer("The brightness of the device, in lumens."),
+            "temperature_in_kelvin": integer("The temperature of the device, in Kelvin."),
+            "minimum_brightness_in_lumen": integer("The minimum brightness of the device, in lumens."),
+            "maximum_brightness_in_lumen": integer("The maximum brightness of the device, in lumens."),
+            "minimum_temperature_in_kelvin": integer("The minimum temperature of the device, in Kelvin."),
+            "maximum_temperature_in_kelvin": integer("The maximum temperature of the device, in Kelvin."),
+            "firmware_version": {
+                "description": "The firmware version of the device, if it could be read.",
+                "type": ["string", "null"]
+            }
+        }
+    })
+}
+
+fn unsupported_device_info_schema() -> Value {
+    json!({
+        "description": "A Logitech device which looks like a light, but isn't supported.",
+        "type": "object",
+        "required": ["product_id", "product_name", "serial_number", "path"],
+        "properties": {
+            "product_id": {
+                "description": "The USB product ID of the device, in hexadecimal.",
+                "type": "string",
+                "pattern": "^0x[0-9a-f]{4}$"
+            },
+            "product_name": { "type": ["string", "null"] },
+            "serial_number": { "type": ["string", "null"] },
+            "path": {
+                "description": "The platform-specific path of the device.",
+                "type": "string"
+            }
+        }
+    })
+}
+
+fn devices_schema() -> Value {
+    json!({
+        "oneOf": [
+            {
+                "description": "The connected devices.",
+                "type": "array",
+                "items": { "$ref": "#/$defs/device_info" }
+            },
+            {
+                "description": "The connected devices and, with `--all`, unsupported devices.",
+                "type": "object",
+                "required": ["devices", "unsupported"],
+                "properties": {
+                    "devices": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/device_info" }
+                    },
+                    "unsupported": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/unsupported_device_info" }
+                    }
+                }
+            }
+        ],
+        "$defs": {
+            "device_info": device_info_schema(),
+            "unsupported_device_info": unsupported_device_info_schema()
+        }
+    })
+}
+
+fn devices_poll_schema() -> Value {
+    json!({
+        "description": "The state of a device at a point in time. Each line of output is a separate JSON document.",
+        "allOf": [{ "$ref": "#/$defs/device_info" }],
+        "required": ["timestamp"],
+        "properties": {
+            "timestamp": {
+                "description": "When the state was read, in RFC 3339 format.",
+                "type": "string",
+                "format": "date-time"
+            }
+        },
+        "$defs": {
+            "device_info": device_info_schema()
+        }
+    })
+}
+
+fn rpc_schema() -> Value {
+    let serial_number = json!({
+        "description": "The serial number of the device. Defaults to the first connected device.",
+        "type": "string"
+    });
+
+    json!({
+        "description": format!(
+            "A single line of JSON-RPC 2.0, using version {} of the protocol.",
+            rpc::PROTOCOL_VERSION
+        ),
+        "oneOf": [
+            { "$ref": "#/$defs/request" },
+            { "$ref": "#/$defs/response" },
+            { "$ref": "#/$defs/notification" }
+        ],
+        "$defs": {
+            "device_info": device_info_schema(),
+            "request": {
+                "description": "A request sent to `litra rpc`. Requests without an `id` don't get a response.",
+                "type": "object",
+                "required": ["jsonrpc", "method"],
+                "properties": {
+                    "jsonrpc": { "const": "2.0" },
+                    "id": { "type": ["string", "number", "null"] },
+                    "method": {
+                        "enum": ["version", "list", "get", "set", "toggle", "subscribe", "unsubscribe"]
+                    },
+                    "params": {
+                        "type": "object",
+                        "properties": {
+                            "serial_number": serial_number,
+                            "on": {
+                                "description": "Used by `set`.",
+                                "type": "boolean"
+                            },
+                            "brightness_in_lumen": {
+                                "description": "Used by `set`.",
+                                "type": "integer",
+                                "minimum": 0,
+                                "maximum": 65535
+                            },
+                            "brightness_percentage": {
+                                "description": "Used by `set`. Can't be combined with `brightness_in_lumen`.",
+                                "type": "integer",
+                                "minimum": 0,
+                                "maximum": 100
+                            },
+                            "temperature_in_kelvin": {
+                                "description": "Used by `set`.",
+                                "type": "integer",
+                                "minimum": 0,
+                                "maximum": 65535
+                            }
+                        }
+                    }
+                }
+            },
+            "response": {
+                "description": "A response to a request with an `id`.",
+                "type": "object",
+                "required": ["jsonrpc", "id"],
+                "properties": {
+                    "jsonrpc": { "const": "2.0" },
+                    "id": { "type": ["string", "number", "null"] },
+                    "result": {
+                        "description": "The result of `get`, `set` and `toggle` is a device's state. `list` and `subscribe` return the state of all connected devices, `version` returns the versions and `unsubscribe` returns null.",
+                        "anyOf": [
+                            { "$ref": "#/$defs/device_info" },
+                            {
+                                "type": "array",
+                                "items": { "$ref": "#/$defs/device_info" }
+                            },
+                            {
+                                "type": "object",
+                                "required": ["protocol_version", "litra_version"],
+                                "properties": {
+                                    "protocol_version": { "type": "integer" },
+                                    "litra_version": { "type": "string" }
+                                }
+                            },
+                            { "type": "null" }
+                        ]
+                    },
+                    "error": {
+                        "type": "object",
+                        "required": ["code", "message"],
+                        "properties": {
+                            "code": { "type": "integer" },
+                            "message": { "type": "string" }
+                        }
+                    }
+                },
+                "oneOf": [
+                    { "required": ["result"] },
+                    { "required": ["error"] }
+                ]
+            },
+            "notification": {
+                "description": "A notification sent by `litra rpc` after `subscribe`.",
+                "type": "object",
+                "required": ["jsonrpc", "method", "params"],
+                "not": { "required": ["id"] },
+                "oneOf": [
+                    {
+                        "properties": {
+                            "jsonrpc": { "const": "2.0" },
+                            "method": { "const": "device_state_changed" },
+                            "params": { "$ref": "#/$defs/device_info" }
+                        }
+                    },
+                    {
+                        "properties": {
+                            "jsonrpc": { "const": "2.0" },
+                            "method": { "const": "device_disconnected" },
+                            "params": {
+                                "type": "object",
+                                "required": ["serial_number"],
+                                "properties": {
+                                    "serial_number": { "type": "string" }
+                                }
+                            }
+                        }
+                    }
+                ]
+            }
+        }
+    })
+}
+
+/// Returns the JSON Schema for a machine-readable output.
+pub fn output_schema(output: Output) -> Value {
+    let mut schema = match output {
+        Output::Devices => devices_schema(),
+        Output::DevicesPoll => devices_poll_schema(),
+        Output::Rpc => rpc_schema(),
+    };
+    schema["$schema"] = json!("https://json-schema.org/draft/2020-12/schema");
+    schema["$id"] = json!(format!(
+        "urn:litra-cli:{}:{}",
+        env!("CARGO_PKG_VERSION"),
+        output.name()
+    ));
+    schema["title"] = json!(format!("litra {} output", output.name()));
+    schema
+}
+
+pub fn handle_schema_command(output: Output) -> CliResult {
+    let schema = serde_json::to_string_pretty(&output_schema(output))
+        .map_err(CliError::SerializationFailed)?;
+    println!("{}", schema);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{devices_json, DeviceInfo, PolledDeviceInfo, UnsupportedDeviceInfo};
+    use std::collections::BTreeSet;
+
+    const OUTPUTS: [Output; 3] = [Output::Devices, Output::DevicesPoll, Output::Rpc];
+
+    fn device_info(firmware_version: Option<&str>) -> DeviceInfo {
+        DeviceInfo {
+            serial_number: "2325FE7044P8".to_string(),
+            device_type: "Litra Glow".to_string(),
+            is_on: true,
+            brightness_in_lumen: 135,
+            temperature_in_kelvin: 4500,
+            minimum_brightness_in_lumen: 20,
+            maximum_brightness_in_lumen: 250,
+            minimum_temperature_in_kelvin: 2700,
+            maximum_temperature_in_kelvin: 6500,
+            firmware_version: firmware_version.map(String::from),
+        }
+    }
+
+    fn unsupported_device_info() -> UnsupportedDeviceInfo {
+        UnsupportedDeviceInfo {
+            product_id: "0xc904".to_string(),
+            product_name: Some("Litra Beam LX".to_string()),
+            serial_number: None,
+            path: "/dev/hidraw3".to_string(),
+        }
+    }
+
+    fn assert_valid(output: Output, instance: &Value) {
+        let validator = jsonschema::validator_for(&output_schema(output)).unwrap();
+        let errors: Vec<String> = validator
+            .iter_errors(instance)
+            .map(|error| error.to_string())
+            .collect();
+        assert!(errors.is_empty(), "{} is invalid: {:?}", instance, errors);
+    }
+
+    fn keys(value: &Value) -> BTreeSet<String> {
+        value.as_object().unwrap().keys().cloned().collect()
+    }
+
+    #[test]
+    fn schemas_are_valid() {
+        for output in OUTPUTS {
+            assert!(jsonschema::meta::is_valid(&output_schema(output)));
+        }
+    }
+
+    #[test]
+    fn devices_output_matches_the_schema() {
+        let devices = [device_info(Some("RQM 1.0.1")), device_info(None)];
+        assert_valid(Output::Devices, &devices_json(&devices, None).unwrap());
+        assert_valid(Output::Devices, &devices_json(&[], None).unwrap());
+        assert_valid(
+            Output::Devices,
+            &devices_json(&devices, Some(&[unsupported_device_info()])).unwrap(),
+        );
+    }
+
+    #[test]
+    fn devices_poll_output_matches_the_schema() {
+        let device_info = device_info(Some("RQM 1.0.1"));
+        let output = serde_json::to_value(PolledDeviceInfo {
+            timestamp: "2024-01-01T12:00:00Z".to_string(),
+            device_info: &device_info,
+        })
+        .unwrap();
+        assert_valid(Output::DevicesPoll, &output);
+    }
+
+    #[test]
+    fn schemas_describe_every_serialized_field() {
+        let schema = device_info_schema();
+        let serialized = serde_json::to_value(device_info(None)).unwrap();
+        assert_eq!(keys(&schema["properties"]), keys(&serialized));
+        assert_eq!(
+            schema["required"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|key| key.as_str().unwrap().to_string())
+                .collect::<BTreeSet<_>>(),
+            keys(&serialized)
+        );
+
+        let serialized = serde_json::to_value(unsupported_device_info()).unwrap();
+        assert_eq!(
+            keys(&unsupported_device_info_schema()["properties"]),
+            keys(&serialized)
+        );
+    }
+}
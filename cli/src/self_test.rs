@@ -0,0 +1,154 @@
+//! Exercises a device by cycling it through power states, its brightness range and every valid
+//! temperature, reading back each setting to check that it was applied. This helps tell a faulty
+//! device apart from a faulty cable or USB port.
+
+use crate::{config, get_first_supported_device, CliError, CliResult};
+use litra::{DeviceHandle, DeviceResult};
+use std::collections::BTreeSet;
+
+/// The number of brightness levels to test, spread evenly across the device's range.
+const BRIGHTNESS_STEPS: u16 = 10;
+
+struct Check {
+    name: String,
+    expected: String,
+    actual: Result<String, String>,
+}
+
+impl Check {
+    fn passed(&self) -> bool {
+        self.actual.as_ref() == Ok(&self.expected)
+    }
+}
+
+fn check<T: ToString + PartialEq>(
+    name: String,
+    expected: T,
+    set: impl FnOnce() -> DeviceResult<()>,
+    get: impl FnOnce() -> DeviceResult<T>,
+) -> Check {
+    let actual = set()
+        .and_then(|()| get())
+        .map(|value| value.to_string())
+        .map_err(|error| error.to_string());
+    Check {
+        name,
+        expected: expected.to_string(),
+        actual,
+    }
+}
+
+fn run_checks(device_handle: &DeviceHandle) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    for on in [true, false, true] {
+        checks.push(check(
+            format!("Power {}", if on { "on" } else { "off" }),
+            on,
+            || device_handle.set_on(on),
+            || device_handle.is_on(),
+        ));
+    }
+
+    // Some devices round brightness to coarser steps, so only test brightnesses they can represent.
+    // If the steps can't be discovered, the checks below will report the device's errors.
+    let brightness_steps = device_handle.discover_brightness_steps().ok();
+    let minimum_brightness = device_handle.minimum_brightness_in_lumen();
+    let maximum_brightness = device_handle.maximum_brightness_in_lumen();
+    let brightnesses: BTreeSet<u16> = (0..=BRIGHTNESS_STEPS)
+        .map(|step| {
+            let brightness = minimum_brightness
+                + (maximum_brightness - minimum_brightness) * step / BRIGHTNESS_STEPS;
+            brightness_steps
+                .as_ref()
+                .map_or(brightness, |steps| steps.snap(brightness))
+        })
+        .collect();
+    for brightness in brightnesses {
+        checks.push(check(
+            format!("Brightness {} lm", brightness),
+            brightness,
+            || device_handle.set_brightness_in_lumen(brightness),
+            || device_handle.brightness_in_lumen(),
+        ));
+    }
+
+    for temperature in device_handle.valid_temperatures_in_kelvin() {
+        checks.push(check(
+            format!("Temperature {} K", temperature),
+            temperature,
+            || device_handle.set_temperature_in_kelvin(temperature),
+            || device_handle.temperature_in_kelvin(),
+        ));
+    }
+
+    checks
+}
+
+pub fn handle_self_test_command(
+    options: &config::GlobalOptions,
+    serial_number: Option<&str>,
+) -> CliResult {
+    let context = config::new_context(options)?;
+    let device_handle = get_first_supported_device(&context, serial_number)?;
+
+    let was_on = device_handle.is_on()?;
+    let brightness_in_lumen = device_handle.brightness_in_lumen()?;
+    let temperature_in_kelvin = device_handle.temperature_in_kelvin()?;
+
+    let checks = run_checks(&device_handle);
+
+    device_handle.set_brightness_in_lumen(brightness_in_lumen)?;
+    device_handle.set_temperature_in_kelvin(temperature_in_kelvin)?;
+    device_handle.set_on(was_on)?;
+
+    let name_width = checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or(0);
+    for check in &checks {
+        let actual = match &check.actual {
+            Ok(value) => value.clone(),
+            Err(error) => format!("error: {}", error),
+        };
+        println!(
+            "{:<width$}  {}  expected {}, got {}",
+            check.name,
+            if check.passed() { "PASS" } else { "FAIL" },
+            check.expected,
+            actual,
+            width = name_width
+        );
+    }
+
+    let failed = checks.iter().filter(|check| !check.passed()).count();
+    if failed > 0 {
+        return Err(CliError::SelfTestFailed(failed, checks.len()));
+    }
+
+    println!("All {} checks passed.", checks.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litra::mock::MockDevice;
+    use litra::DeviceType;
+
+    #[test]
+    fn passes_devices_which_round_brightness() {
+        let device = MockDevice::new(DeviceType::LitraBeam, "2325FE7044P8");
+        device.set_brightness_rounding_in_lumen(50);
+        let checks = run_checks(&device.open());
+
+        let failed: Vec<_> = checks
+            .iter()
+            .filter(|check| !check.passed())
+            .map(|check| &check.name)
+            .collect();
+        assert!(failed.is_empty(), "{:?}", failed);
+        assert!(checks.iter().any(|check| check.name == "Brightness 130 lm"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Named snapshots of the state of all connected devices, stored as JSON files in a `snapshots`
+//! directory next to the configuration file, so that different setups can be switched between.
+
+use crate::{
+    config, describe_device, explain_open_error, summarize_device_results, CliError, CliResult,
+};
+use litra::{DeviceHandle, DeviceResult};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// The state of a single device in a snapshot.
+#[derive(Debug, Serialize, Deserialize)]
+struct DeviceState {
+    is_on: bool,
+    brightness_in_lumen: u16,
+    temperature_in_kelvin: u16,
+}
+
+impl DeviceState {
+    fn apply(&self, device_handle: &DeviceHandle) -> DeviceResult<()> {
+        device_handle.set_brightness_in_lumen(self.brightness_in_lumen)?;
+        device_handle.set_temperature_in_kelvin(self.temperature_in_kelvin)?;
+        device_handle.set_on(self.is_on)
+    }
+}
+
+/// The state of each device, keyed by serial number.
+type Snapshot = BTreeMap<String, DeviceState>;
+
+fn snapshot_error(error: impl std::fmt::Display) -> CliError {
+    CliError::SnapshotFailed(error.to_string())
+}
+
+fn snapshots_directory() -> Result<PathBuf, CliError> {
+    config::config_path()
+        .and_then(|path| path.parent().map(|directory| directory.join("snapshots")))
+        .ok_or_else(|| snapshot_error("Couldn't find the configuration directory"))
+}
+
+fn snapshot_path(name: &str) -> Result<PathBuf, CliError> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|character| character.is_alphanumeric() || character == '-' || character == '_')
+    {
+        return Err(snapshot_error(
+            "Snapshot names can only contain letters, numbers, `-` and `_`",
+        ));
+    }
+    Ok(snapshots_directory()?.join(format!("{}.json", name)))
+}
+
+pub fn handle_snapshot_save_command(options: &config::GlobalOptions, name: &str) -> CliResult {
+    let path = snapshot_path(name)?;
+    let context = config::new_context(options)?;
+
+    let mut snapshot = Snapshot::new();
+    for device in context.get_connected_devices() {
+        let Some(serial_number) = device.serial_number() else {
+            continue;
+        };
+        let device_handle = device.open(&context).map_err(explain_open_error)?;
+        snapshot.insert(
+            serial_number.to_string(),
+            DeviceState {
+                is_on: device_handle.is_on()?,
+                brightness_in_lumen: device_handle.brightness_in_lumen()?,
+                temperature_in_kelvin: device_handle.temperature_in_kelvin()?,
+            },
+        );
+    }
+    if snapshot.is_empty() {
+        return Err(CliError::DeviceNotFound);
+    }
+
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(snapshot_error)?;
+    }
+    let json = serde_json::to_string_pretty(&snapshot).map_err(CliError::SerializationFailed)?;
+    fs::write(&path, json).map_err(snapshot_error)?;
+
+    println!(
+        "Saved the state of {} device(s) to snapshot `{}`",
+        snapshot.len(),
+        name
+    );
+    Ok(())
+}
+
+pub fn handle_snapshot_restore_command(options: &config::GlobalOptions, name: &str) -> CliResult {
+    let path = snapshot_path(name)?;
+    let json = fs::read_to_string(&path).map_err(|error| match error.kind() {
+        io::ErrorKind::NotFound => snapshot_error(format!("Snapshot `{}` doesn't exist", name)),
+        _ => snapshot_error(error),
+    })?;
+    let mut snapshot: Snapshot =
+        serde_json::from_str(&json).map_err(CliError::SerializationFailed)?;
+
+    let context = config::new_context(options)?;
+    let mut results = Vec::new();
+    for device in context.get_connected_devices() {
+        let Some(state) = device
+            .serial_number()
+            .and_then(|serial_number| snapshot.remove(serial_number))
+        else {
+            continue;
+        };
+        let result = device
+            .open(&context)
+            .map_err(explain_open_error)
+            .and_then(|device_handle| state.apply(&device_handle).map_err(CliError::from));
+        match result {
+            Err(error) if options.strict => return Err(error),
+            result => results.push((describe_device(&device), result)),
+        }
+    }
+
+    for serial_number in snapshot.keys() {
+        eprintln!(
+            "Device {} from snapshot `{}` isn't connected, so it was skipped",
+            serial_number, name
+        );
+    }
+    if results.is_empty() {
+        return Err(CliError::DeviceNotFound);
+    }
+    summarize_device_results(results)
+}
+
+pub fn handle_snapshot_list_command() -> CliResult {
+    let entries = match fs::read_dir(snapshots_directory()?) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            println!("No snapshots found");
+            return Ok(());
+        }
+        Err(error) => return Err(snapshot_error(error)),
+    };
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect();
+    names.sort();
+
+    if names.is_empty() {
+        println!("No snapshots found");
+    }
+    for name in names {
+        println!("- {}", name);
+    }
+    Ok(())
+}
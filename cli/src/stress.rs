@@ -0,0 +1,155 @@
+//! Repeatedly sends random valid operations to a device for a long period, recording error rates
+//! and timings, to reproduce intermittent failures which only show up after hours of use.
+
+use crate::bench::Timings;
+use crate::{config, get_first_supported_device, CliResult};
+use litra::{DeviceHandle, DeviceResult};
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How often to print a summary while the test is running.
+const SUMMARY_INTERVAL: Duration = Duration::from_secs(60);
+
+const OPERATIONS: [&str; 6] = [
+    "Get power",
+    "Set power",
+    "Get brightness",
+    "Set brightness",
+    "Get temperature",
+    "Set temperature",
+];
+
+/// A small xorshift pseudo-random number generator. The operations only need to vary, so this
+/// avoids a dependency.
+struct Random(u64);
+
+impl Random {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+        Random(seed | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a number in the inclusive range.
+    fn between(&mut self, minimum: u16, maximum: u16) -> u16 {
+        minimum + (self.next() % u64::from(maximum - minimum + 1)) as u16
+    }
+}
+
+#[derive(Default)]
+struct OperationStats {
+    timings: Timings,
+    errors: BTreeMap<String, usize>,
+}
+
+fn run_operation(
+    device_handle: &DeviceHandle,
+    operation: usize,
+    random: &mut Random,
+) -> DeviceResult<()> {
+    match operation {
+        0 => device_handle.is_on().map(|_| ()),
+        1 => device_handle.set_on(random.next() % 2 == 0),
+        2 => device_handle.brightness_in_lumen().map(|_| ()),
+        3 => device_handle.set_brightness_in_lumen(random.between(
+            device_handle.minimum_brightness_in_lumen(),
+            device_handle.maximum_brightness_in_lumen(),
+        )),
+        4 => device_handle.temperature_in_kelvin().map(|_| ()),
+        _ => {
+            let temperatures: Vec<u16> = device_handle.valid_temperatures_in_kelvin().collect();
+            let index = random.between(0, temperatures.len() as u16 - 1);
+            device_handle.set_temperature_in_kelvin(temperatures[usize::from(index)])
+        }
+    }
+}
+
+fn print_summary(stats: &[OperationStats], elapsed: Duration) {
+    let total: usize = stats.iter().map(|stats| stats.timings.len()).sum();
+    let failed: usize = stats.iter().flat_map(|stats| stats.errors.values()).sum();
+    println!(
+        "After {:.0?}: {} operations, {} failed ({:.2}%)",
+        elapsed,
+        total,
+        failed,
+        if total == 0 {
+            0.0
+        } else {
+            failed as f64 * 100.0 / total as f64
+        }
+    );
+    for (name, stats) in OPERATIONS.iter().zip(stats) {
+        println!("- {}: {}", name, stats.timings.summary());
+        for (error, count) in &stats.errors {
+            println!("  - {} x {}", count, error);
+        }
+    }
+}
+
+pub fn handle_stress_command(
+    options: &config::GlobalOptions,
+    serial_number: Option<&str>,
+    duration: Duration,
+    operations_per_second: u32,
+) -> CliResult {
+    let context = config::new_context(options)?;
+    let device_handle = get_first_supported_device(&context, serial_number)?;
+
+    let was_on = device_handle.is_on()?;
+    let brightness_in_lumen = device_handle.brightness_in_lumen()?;
+    let temperature_in_kelvin = device_handle.temperature_in_kelvin()?;
+
+    let interval = Duration::from_secs(1) / operations_per_second.max(1);
+    let mut random = Random::new();
+    let mut stats: Vec<OperationStats> = OPERATIONS
+        .iter()
+        .map(|_| OperationStats::default())
+        .collect();
+
+    let start = Instant::now();
+    let mut last_summary = start;
+    while start.elapsed() < duration {
+        let next = Instant::now() + interval;
+
+        let operation = (random.next() % OPERATIONS.len() as u64) as usize;
+        let result = stats[operation]
+            .timings
+            .measure(|| run_operation(&device_handle, operation, &mut random));
+        if let Err(error) = result {
+            eprintln!(
+                "[{:.0?}] {} failed: {}",
+                start.elapsed(),
+                OPERATIONS[operation],
+                error
+            );
+            *stats[operation]
+                .errors
+                .entry(error.to_string())
+                .or_default() += 1;
+        }
+
+        if last_summary.elapsed() >= SUMMARY_INTERVAL {
+            last_summary = Instant::now();
+            print_summary(&stats, start.elapsed());
+        }
+
+        thread::sleep(next.saturating_duration_since(Instant::now()));
+    }
+
+    print_summary(&stats, start.elapsed());
+
+    device_handle.set_brightness_in_lumen(brightness_in_lumen)?;
+    device_handle.set_temperature_in_kelvin(temperature_in_kelvin)?;
+    device_handle.set_on(was_on)?;
+    Ok(())
+}
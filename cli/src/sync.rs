@@ -0,0 +1,151 @@
+//! Mirrors the state of one device to others, so that several lights stay matched even when one is
+//! adjusted using its buttons.
+
+use crate::{check_serial_number_if_some, config, explain_open_error, CliError, CliResult};
+use litra::{DeviceHandle, DeviceResult};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct State {
+    is_on: bool,
+    brightness_in_lumen: u16,
+    temperature_in_kelvin: u16,
+}
+
+impl State {
+    fn read(device_handle: &DeviceHandle) -> DeviceResult<Self> {
+        Ok(State {
+            is_on: device_handle.is_on()?,
+            brightness_in_lumen: device_handle.brightness_in_lumen()?,
+            temperature_in_kelvin: device_handle.temperature_in_kelvin()?,
+        })
+    }
+
+    /// Applies the state to a device. The values are clamped to the device's range, since the
+    /// devices being kept in sync may be different models.
+    fn apply(&self, device_handle: &DeviceHandle) -> DeviceResult<()> {
+        device_handle.set_brightness_in_lumen(self.brightness_in_lumen.clamp(
+            device_handle.minimum_brightness_in_lumen(),
+            device_handle.maximum_brightness_in_lumen(),
+        ))?;
+        device_handle.set_temperature_in_kelvin(self.temperature_in_kelvin.clamp(
+            device_handle.minimum_temperature_in_kelvin(),
+            device_handle.maximum_temperature_in_kelvin(),
+        ))?;
+        device_handle.set_on(self.is_on)
+    }
+}
+
+/// Applies the state to every follower, returning whether they were all updated. Unless `strict`
+/// is set, failures are reported and the other followers are still updated.
+fn update_followers(
+    state: &State,
+    followers: &[(String, DeviceHandle)],
+    strict: bool,
+) -> Result<bool, CliError> {
+    let mut all_updated = true;
+    for (serial_number, device_handle) in followers {
+        if let Err(error) = state.apply(device_handle) {
+            if strict {
+                return Err(error.into());
+            }
+            eprintln!("Failed to update {}: {}", serial_number, error);
+            all_updated = false;
+        }
+    }
+    Ok(all_updated)
+}
+
+pub fn handle_sync_command(
+    options: &config::GlobalOptions,
+    master_serial_number: &str,
+    serial_numbers: &[String],
+    poll_interval: Duration,
+) -> CliResult {
+    let context = config::new_context(options)?;
+
+    let master = context
+        .get_connected_devices()
+        .find(check_serial_number_if_some(Some(master_serial_number)))
+        .ok_or(CliError::DeviceNotFound)?
+        .open(&context)
+        .map_err(explain_open_error)?;
+
+    let mut followers = Vec::new();
+    for device in context.get_connected_devices() {
+        let Some(serial_number) = device.serial_number() else {
+            continue;
+        };
+        if serial_number == master_serial_number
+            || !(serial_numbers.is_empty() || serial_numbers.iter().any(|s| s == serial_number))
+        {
+            continue;
+        }
+        followers.push((
+            serial_number.to_string(),
+            device.open(&context).map_err(explain_open_error)?,
+        ));
+    }
+    if followers.is_empty() {
+        return Err(CliError::DeviceNotFound);
+    }
+
+    println!(
+        "Mirroring {} to {}. Press Ctrl+C to stop.",
+        master_serial_number,
+        followers
+            .iter()
+            .map(|(serial_number, _)| serial_number.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // The last state applied to every follower. This isn't updated while any follower fails, so
+    // that they are retried on the next poll rather than only when the master changes again
+    let mut last_state = None;
+    loop {
+        match State::read(&master) {
+            Ok(state) if last_state != Some(state) => {
+                if update_followers(&state, &followers, options.strict)? {
+                    last_state = Some(state);
+                }
+            }
+            Ok(_) => {}
+            // Failures are reported without giving up, since the device may only be briefly busy
+            Err(error) => eprintln!("Failed to read {}: {}", master_serial_number, error),
+        }
+        thread::sleep(poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litra::mock::MockDevice;
+    use litra::DeviceType;
+
+    const STATE: State = State {
+        is_on: true,
+        brightness_in_lumen: 200,
+        temperature_in_kelvin: 4000,
+    };
+
+    #[test]
+    fn reports_whether_every_follower_was_updated() {
+        let working = MockDevice::new(DeviceType::LitraBeam, "2325FE7044P8");
+        let failing = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P9");
+        let followers = [
+            ("2325FE7044P9".to_string(), failing.open()),
+            ("2325FE7044P8".to_string(), working.open()),
+        ];
+        assert!(update_followers(&STATE, &followers, false).unwrap());
+
+        failing.disconnect();
+        working.set_on(false);
+        assert!(!update_followers(&STATE, &followers, false).unwrap());
+        assert!(working.is_on());
+
+        assert!(update_followers(&STATE, &followers, true).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! Matches the temperature of devices to a white point, either a standard illuminant like D65 or
+//! the white point of a display's ICC profile, so that a key light can be kept consistent with a
+//! calibrated monitor.
+
+use crate::{config, for_each_target_device, CliError, CliResult, DeviceTarget};
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "linux")]
+use std::process::Command;
+
+/// The correlated color temperatures of the CIE standard illuminants, in Kelvin.
+const ILLUMINANTS: [(&str, f64); 4] = [
+    ("D50", 5003.0),
+    ("D55", 5503.0),
+    ("D65", 6504.0),
+    ("D75", 7504.0),
+];
+
+const ICC_HEADER_SIZE: usize = 128;
+/// The size of each entry in an ICC profile's tag table: a signature, an offset and a size.
+const TAG_ENTRY_SIZE: usize = 12;
+
+fn match_error(message: impl Into<String>) -> CliError {
+    CliError::MatchFailed(message.into())
+}
+
+/// Parses a white point given as the name of a standard illuminant, or a temperature in Kelvin.
+/// Temperatures must be finite and positive.
+pub fn parse_white_point(value: &str) -> Result<f64, String> {
+    ILLUMINANTS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, temperature_in_kelvin)| *temperature_in_kelvin)
+        .or_else(|| {
+            value
+                .trim_end_matches(['K', 'k'])
+                .parse()
+                .ok()
+                .filter(|temperature_in_kelvin: &f64| {
+                    temperature_in_kelvin.is_finite() && *temperature_in_kelvin > 0.0
+                })
+        })
+        .ok_or_else(|| {
+            format!(
+                "Expected a standard illuminant ({}) or a temperature in Kelvin",
+                ILLUMINANTS.map(|(name, _)| name).join(", ")
+            )
+        })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Reads an ICC `s15Fixed16Number`.
+fn read_fixed(data: &[u8], offset: usize) -> Option<f64> {
+    Some(f64::from(read_u32(data, offset)? as i32) / 65536.0)
+}
+
+/// Returns the data of the tag with the given signature from an ICC profile.
+fn find_tag<'a>(profile: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    // The tag count is limited to the number of entries which fit in the profile, so that a
+    // corrupt count doesn't make us look through billions of entries which aren't there
+    let maximum_count = profile.len().saturating_sub(ICC_HEADER_SIZE + 4) / TAG_ENTRY_SIZE;
+    let count = usize::try_from(read_u32(profile, ICC_HEADER_SIZE)?)
+        .ok()?
+        .min(maximum_count);
+    (0..count).find_map(|index| {
+        let entry = ICC_HEADER_SIZE + 4 + index * TAG_ENTRY_SIZE;
+        if profile.get(entry..entry + 4)? != signature {
+            return None;
+        }
+        let offset = usize::try_from(read_u32(profile, entry + 4)?).ok()?;
+        let size = usize::try_from(read_u32(profile, entry + 8)?).ok()?;
+        profile.get(offset..offset.checked_add(size)?)
+    })
+}
+
+fn read_xyz(tag: &[u8]) -> Option<[f64; 3]> {
+    if tag.get(..4)? != b"XYZ " {
+        return None;
+    }
+    Some([
+        read_fixed(tag, 8)?,
+        read_fixed(tag, 12)?,
+        read_fixed(tag, 16)?,
+    ])
+}
+
+fn read_matrix(tag: &[u8]) -> Option<[f64; 9]> {
+    if tag.get(..4)? != b"sf32" {
+        return None;
+    }
+    let mut matrix = [0.0; 9];
+    for (index, value) in matrix.iter_mut().enumerate() {
+        *value = read_fixed(tag, 8 + index * 4)?;
+    }
+    Some(matrix)
+}
+
+/// Solves `matrix * result = vector` with Cramer's rule.
+fn solve(matrix: [f64; 9], vector: [f64; 3]) -> Option<[f64; 3]> {
+    let determinant = |m: [f64; 9]| {
+        m[0] * (m[4] * m[8] - m[5] * m[7]) - m[1] * (m[3] * m[8] - m[5] * m[6])
+            + m[2] * (m[3] * m[7] - m[4] * m[6])
+    };
+    let denominator = determinant(matrix);
+    if denominator.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let mut result = [0.0; 3];
+    for (column, value) in result.iter_mut().enumerate() {
+        let mut replaced = matrix;
+        for row in 0..3 {
+            replaced[row * 3 + column] = vector[row];
+        }
+        *value = determinant(replaced) / denominator;
+    }
+    Some(result)
+}
+
+/// Reads the white point of a display from its ICC profile, as CIE XYZ.
+fn read_icc_white_point(path: &Path) -> Result<[f64; 3], CliError> {
+    let profile = fs::read(path).map_err(|error| match_error(error.to_string()))?;
+    icc_white_point(&profile)
+}
+
+fn icc_white_point(profile: &[u8]) -> Result<[f64; 3], CliError> {
+    if profile.get(36..40) != Some(b"acsp") {
+        return Err(match_error("The file isn't an ICC profile"));
+    }
+
+    let white_point = find_tag(profile, b"wtpt")
+        .and_then(read_xyz)
+        .ok_or_else(|| match_error("The ICC profile doesn't have a white point"))?;
+
+    // From version 4, the white point is always D50, and the display's actual white point has to
+    // be recovered with the inverse of the chromatic adaptation matrix
+    let major_version = profile[8];
+    match find_tag(profile, b"chad").and_then(read_matrix) {
+        Some(matrix) if major_version >= 4 => solve(matrix, white_point)
+            .ok_or_else(|| match_error("The ICC profile's adaptation matrix is invalid")),
+        _ => Ok(white_point),
+    }
+}
+
+/// Finds the ICC profile of the display using colord's `colormgr` command. Displays are checked in
+/// the order colord lists them, and the first with a default profile is used.
+#[cfg(target_os = "linux")]
+fn display_icc_profile() -> Result<PathBuf, CliError> {
+    let devices = colormgr(&["get-devices-by-kind", "display"])?;
+    field_values(&devices, "Device ID")
+        .into_iter()
+        .find_map(|device_id| {
+            let profile = colormgr(&["device-get-default-profile", device_id]).ok()?;
+            field_values(&profile, "Filename").first().map(PathBuf::from)
+        })
+        .ok_or_else(|| {
+            match_error("colord doesn't have an ICC profile for any display. Pass the path of one with `--icc-profile` instead")
+        })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn display_icc_profile() -> Result<PathBuf, CliError> {
+    Err(match_error(
+        "`--display` is only supported on Linux, where it uses colord. Pass the path of your display's ICC profile with `--icc-profile` instead",
+    ))
+}
+
+/// Runs `colormgr`, returning its output. Its output is translated, so it's run in the C locale to
+/// get English field names.
+#[cfg(target_os = "linux")]
+fn colormgr(args: &[&str]) -> Result<String, CliError> {
+    let output = Command::new("colormgr")
+        .args(args)
+        .env("LC_ALL", "C")
+        .output()
+        .map_err(|error| {
+            match_error(format!(
+                "Failed to run colormgr, which is part of colord: {}",
+                error
+            ))
+        })?;
+    if !output.status.success() {
+        return Err(match_error(format!(
+            "colormgr failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Returns the values of the fields with the given name in `colormgr` output, where each field is
+/// a line like `Device ID:     xrandr-Dell-U2720Q`.
+#[cfg(target_os = "linux")]
+fn field_values<'a>(output: &'a str, name: &str) -> Vec<&'a str> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (field, value) = line.split_once(':')?;
+            (field.trim() == name).then(|| value.trim())
+        })
+        .collect()
+}
+
+/// Estimates the correlated color temperature of a CIE XYZ color using McCamy's approximation.
+fn xyz_to_temperature_in_kelvin([x, y, z]: [f64; 3]) -> Option<f64> {
+    let sum = x + y + z;
+    if sum <= 0.0 {
+        return None;
+    }
+    let n = (x / sum - 0.3320) / (0.1858 - y / sum);
+    Some(449.0 * n.powi(3) + 3525.0 * n.powi(2) + 6823.3 * n + 5520.33)
+}
+
+pub fn handle_match_command(
+    options: &config::GlobalOptions,
+    target: &DeviceTarget,
+    white_point: Option<f64>,
+    icc_profile: Option<&Path>,
+    display: bool,
+) -> CliResult {
+    let icc_profile = match icc_profile {
+        Some(path) => Some(path.to_path_buf()),
+        None if display => {
+            let path = display_icc_profile()?;
+            println!("Using the display's ICC profile at {}", path.display());
+            Some(path)
+        }
+        None => None,
+    };
+    let white_point_in_kelvin = match (white_point, icc_profile) {
+        (Some(temperature_in_kelvin), _) => temperature_in_kelvin,
+        (None, Some(path)) => xyz_to_temperature_in_kelvin(read_icc_white_point(&path)?)
+            .ok_or_else(|| match_error("The ICC profile's white point is invalid"))?,
+        (None, None) => return Err(match_error("No white point was given")),
+    };
+
+    for_each_target_device(options, target, |device_handle| {
+        let step = f64::from(device_handle.temperature_step_in_kelvin());
+        let temperature_in_kelvin = ((white_point_in_kelvin / step).round() * step).clamp(
+            f64::from(device_handle.minimum_temperature_in_kelvin()),
+            f64::from(device_handle.maximum_temperature_in_kelvin()),
+        ) as u16;
+        println!(
+            "Setting {} to {} K to match a white point of {:.0} K",
+            device_handle.device_type(),
+            temperature_in_kelvin,
+            white_point_in_kelvin
+        );
+        device_handle.set_temperature_in_kelvin(temperature_in_kelvin)?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+    const D65: [f64; 3] = [0.9505, 1.0, 1.0890];
+    /// The Bradford chromatic adaptation matrix from D65 to D50, as stored in a `chad` tag.
+    const D65_TO_D50: [f64; 9] = [
+        1.0479, 0.0229, -0.0502, 0.0296, 0.9904, -0.0171, -0.0092, 0.0151, 0.7519,
+    ];
+
+    fn fixed(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+
+    fn xyz_tag(xyz: [f64; 3]) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        tag.extend(xyz.into_iter().flat_map(fixed));
+        tag
+    }
+
+    fn matrix_tag(matrix: [f64; 9]) -> Vec<u8> {
+        let mut tag = b"sf32\0\0\0\0".to_vec();
+        tag.extend(matrix.into_iter().flat_map(fixed));
+        tag
+    }
+
+    /// Builds an ICC profile with the major version and tags, leaving the rest of the header empty.
+    fn profile(major_version: u8, tags: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut profile = vec![0x00; ICC_HEADER_SIZE];
+        profile[8] = major_version;
+        profile[36..40].copy_from_slice(b"acsp");
+
+        let count = u32::try_from(tags.len()).unwrap();
+        profile.extend_from_slice(&count.to_be_bytes());
+        let mut offset = ICC_HEADER_SIZE + 4 + tags.len() * 12;
+        for (signature, data) in tags {
+            profile.extend_from_slice(*signature);
+            profile.extend_from_slice(&u32::try_from(offset).unwrap().to_be_bytes());
+            profile.extend_from_slice(&u32::try_from(data.len()).unwrap().to_be_bytes());
+            offset += data.len();
+        }
+        for (_, data) in tags {
+            profile.extend_from_slice(data);
+        }
+        profile
+    }
+
+    fn assert_close(actual: [f64; 3], expected: [f64; 3]) {
+        for (actual, expected) in actual.into_iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 0.001,
+                "{} != {}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn reads_the_white_point_of_version_2_profiles() {
+        let profile = profile(2, &[(b"wtpt", xyz_tag(D65))]);
+        assert_close(icc_white_point(&profile).unwrap(), D65);
+    }
+
+    #[test]
+    fn ignores_the_adaptation_matrix_of_version_2_profiles() {
+        let profile = profile(
+            2,
+            &[(b"chad", matrix_tag(D65_TO_D50)), (b"wtpt", xyz_tag(D65))],
+        );
+        assert_close(icc_white_point(&profile).unwrap(), D65);
+    }
+
+    #[test]
+    fn recovers_the_white_point_of_version_4_profiles() {
+        let profile = profile(
+            4,
+            &[(b"wtpt", xyz_tag(D50)), (b"chad", matrix_tag(D65_TO_D50))],
+        );
+        assert_close(icc_white_point(&profile).unwrap(), D65);
+    }
+
+    #[test]
+    fn uses_the_white_point_of_version_4_profiles_without_an_adaptation_matrix() {
+        let profile = profile(4, &[(b"wtpt", xyz_tag(D50))]);
+        assert_close(icc_white_point(&profile).unwrap(), D50);
+    }
+
+    #[test]
+    fn rejects_invalid_profiles() {
+        let mut not_a_profile = profile(2, &[(b"wtpt", xyz_tag(D65))]);
+        not_a_profile[36..40].copy_from_slice(b"xxxx");
+        assert!(icc_white_point(&not_a_profile).is_err());
+
+        assert!(icc_white_point(&profile(2, &[])).is_err());
+        assert!(icc_white_point(&profile(2, &[(b"wtpt", matrix_tag(D65_TO_D50))])).is_err());
+
+        let mut truncated = profile(2, &[(b"wtpt", xyz_tag(D65))]);
+        truncated.truncate(truncated.len() - 2);
+        assert!(icc_white_point(&truncated).is_err());
+
+        let singular_matrix = [0.0; 9];
+        let profile = profile(
+            4,
+            &[
+                (b"wtpt", xyz_tag(D50)),
+                (b"chad", matrix_tag(singular_matrix)),
+            ],
+        );
+        assert!(icc_white_point(&profile).is_err());
+    }
+
+    #[test]
+    fn limits_corrupt_tag_counts_to_the_size_of_the_profile() {
+        let mut corrupt_count = profile(2, &[(b"desc", vec![0x00; 4]), (b"wtpt", xyz_tag(D65))]);
+        corrupt_count[ICC_HEADER_SIZE..ICC_HEADER_SIZE + 4]
+            .copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_close(icc_white_point(&corrupt_count).unwrap(), D65);
+
+        let mut no_white_point = profile(2, &[(b"desc", vec![0x00; 4])]);
+        no_white_point[ICC_HEADER_SIZE..ICC_HEADER_SIZE + 4]
+            .copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(icc_white_point(&no_white_point).is_err());
+
+        let mut overflowing_size = profile(2, &[(b"wtpt", xyz_tag(D65))]);
+        overflowing_size[ICC_HEADER_SIZE + 12..ICC_HEADER_SIZE + 16]
+            .copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(icc_white_point(&overflowing_size).is_err());
+    }
+
+    #[test]
+    fn estimates_the_temperature_of_standard_illuminants() {
+        for (xyz, expected) in [(D50, 5003.0), (D65, 6504.0)] {
+            let temperature_in_kelvin = xyz_to_temperature_in_kelvin(xyz).unwrap();
+            assert!(
+                (temperature_in_kelvin - expected).abs() < 15.0,
+                "{} != {}",
+                temperature_in_kelvin,
+                expected
+            );
+        }
+        assert_eq!(xyz_to_temperature_in_kelvin([0.0, 0.0, 0.0]), None);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn reads_fields_from_colormgr_output() {
+        let devices = "Object Path:   /org/freedesktop/ColorManager/devices/xrandr_Dell_U2720Q\n\
+                       Type:          display\n\
+                       Device ID:     xrandr-Dell-U2720Q\n\
+                       Profile 1:     icc-6f2a\n\
+                       \n\
+                       Object Path:   /org/freedesktop/ColorManager/devices/xrandr_eDP_1\n\
+                       Device ID:     xrandr-eDP-1\n";
+        assert_eq!(
+            field_values(devices, "Device ID"),
+            ["xrandr-Dell-U2720Q", "xrandr-eDP-1"]
+        );
+
+        let profile = "Object Path:   /org/freedesktop/ColorManager/profiles/icc_6f2a\n\
+                       Filename:      /home/user/.local/share/icc/edid-6f2a.icc\n\
+                       Title:         Dell U2720Q: calibrated\n";
+        assert_eq!(
+            field_values(profile, "Filename"),
+            ["/home/user/.local/share/icc/edid-6f2a.icc"]
+        );
+        assert_eq!(field_values(profile, "Title"), ["Dell U2720Q: calibrated"]);
+        assert!(field_values(profile, "Device ID").is_empty());
+    }
+
+    #[test]
+    fn parses_white_points() {
+        assert_eq!(parse_white_point("D65"), Ok(6504.0));
+        assert_eq!(parse_white_point("d50"), Ok(5003.0));
+        assert_eq!(parse_white_point("5600"), Ok(5600.0));
+        assert_eq!(parse_white_point("5600K"), Ok(5600.0));
+        assert!(parse_white_point("daylight").is_err());
+        for invalid in [
+            "nan", "NaN", "inf", "-inf", "infinity", "-5600", "0", "0K", "-0",
+        ] {
+            assert!(parse_white_point(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
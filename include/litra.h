@@ -0,0 +1,216 @@
+/* Generated with cbindgen from src/ffi.rs. Do not edit by hand. */
+
+#ifndef LITRA_H
+#define LITRA_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The maximum length of a serial number returned by [`litra_list_devices`], including the
+// terminating NUL byte.
+#define LITRA_SERIAL_NUMBER_LENGTH 64
+
+// The result of a C API call.
+typedef enum LitraStatus {
+  // The call succeeded.
+  LITRA_STATUS_OK = 0,
+  // A required pointer was `NULL` or a string wasn't valid UTF-8.
+  LITRA_STATUS_INVALID_ARGUMENT = 1,
+  // No matching device was found.
+  LITRA_STATUS_DEVICE_NOT_FOUND = 2,
+  // The device is not supported.
+  LITRA_STATUS_UNSUPPORTED = 3,
+  // The brightness is outside of the range supported by the device.
+  LITRA_STATUS_INVALID_BRIGHTNESS = 4,
+  // The temperature is outside of the range supported by the device, or isn't a multiple
+  // of the device's temperature step.
+  LITRA_STATUS_INVALID_TEMPERATURE = 5,
+  // Communicating with the device failed.
+  LITRA_STATUS_HID_ERROR = 6,
+  // The device didn't respond in time.
+  LITRA_STATUS_TIMEOUT = 7,
+} LitraStatus;
+
+// The model of a device.
+typedef enum LitraDeviceType {
+  // Logitech Litra Glow.
+  LITRA_DEVICE_TYPE_LITRA_GLOW = 0,
+  // Logitech Litra Beam.
+  LITRA_DEVICE_TYPE_LITRA_BEAM = 1,
+  // Logitech Litra Beam LX.
+  LITRA_DEVICE_TYPE_LITRA_BEAM_LX = 2,
+} LitraDeviceType;
+
+// An opaque Litra context, created with [`litra_context_new`].
+typedef struct LitraContext LitraContext;
+
+// An opaque handle to an opened device, created with [`litra_device_open`] or
+// [`litra_device_open_by_serial_number`].
+typedef struct LitraDevice LitraDevice;
+
+// A connected device, as returned by [`litra_list_devices`].
+typedef struct LitraDeviceInfo {
+  // The model of the device.
+  enum LitraDeviceType device_type;
+  // The NUL-terminated serial number of the device. This is empty if the device doesn't report
+  // a serial number.
+  char serial_number[LITRA_SERIAL_NUMBER_LENGTH];
+} LitraDeviceInfo;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates a new Litra context. Returns `NULL` if initialization failed. The context must be
+// freed with [`litra_context_free`].
+struct LitraContext *litra_context_new(void);
+
+// Frees a context created with [`litra_context_new`]. Passing `NULL` is a no-op.
+//
+// # Safety
+//
+// `context` must be `NULL` or a pointer returned by [`litra_context_new`] which hasn't already
+// been freed.
+void litra_context_free(struct LitraContext *context);
+
+// Refreshes the list of connected devices.
+//
+// # Safety
+//
+// `context` must be a valid pointer returned by [`litra_context_new`].
+enum LitraStatus litra_refresh_devices(struct LitraContext *context);
+
+// Writes up to `capacity` connected devices into `devices`, and returns the total number of
+// connected devices. Call with `capacity` set to `0` to find out how much space is needed.
+//
+// # Safety
+//
+// `context` must be a valid pointer returned by [`litra_context_new`]. `devices` must point to at
+// least `capacity` writable [`LitraDeviceInfo`] values, and may only be `NULL` if `capacity`
+// is `0`.
+size_t litra_list_devices(const struct LitraContext *context,
+                          struct LitraDeviceInfo *devices,
+                          size_t capacity);
+
+// Opens the connected device at `index` in the list returned by [`litra_list_devices`]. The
+// device must be closed with [`litra_device_close`].
+//
+// # Safety
+//
+// `context` must be a valid pointer returned by [`litra_context_new`], and `device` must point
+// to writable memory for a device pointer.
+enum LitraStatus litra_device_open(const struct LitraContext *context,
+                                   size_t index,
+                                   struct LitraDevice **device);
+
+// Opens the connected device with the given NUL-terminated serial number. The device must be
+// closed with [`litra_device_close`].
+//
+// # Safety
+//
+// `context` must be a valid pointer returned by [`litra_context_new`], `serial_number` must be a
+// valid NUL-terminated string, and `device` must point to writable memory for a device pointer.
+enum LitraStatus litra_device_open_by_serial_number(const struct LitraContext *context,
+                                                    const char *serial_number,
+                                                    struct LitraDevice **device);
+
+// Closes a device opened with [`litra_device_open`] or [`litra_device_open_by_serial_number`].
+// Passing `NULL` is a no-op.
+//
+// # Safety
+//
+// `device` must be `NULL` or a pointer returned by one of the open functions which hasn't already
+// been closed.
+void litra_device_close(struct LitraDevice *device);
+
+// Returns the model of the device.
+//
+// # Safety
+//
+// `device` must be a valid, open device.
+enum LitraDeviceType litra_device_type(const struct LitraDevice *device);
+
+// Queries whether the device is on, writing the result to `is_on`.
+//
+// # Safety
+//
+// `device` must be a valid, open device and `is_on` must point to writable memory.
+enum LitraStatus litra_is_on(const struct LitraDevice *device, bool *is_on);
+
+// Turns the device on or off.
+//
+// # Safety
+//
+// `device` must be a valid, open device.
+enum LitraStatus litra_set_on(const struct LitraDevice *device, bool on);
+
+// Queries the device's brightness in Lumen, writing the result to `brightness_in_lumen`.
+//
+// # Safety
+//
+// `device` must be a valid, open device and `brightness_in_lumen` must point to writable memory.
+enum LitraStatus litra_get_brightness(const struct LitraDevice *device,
+                                      uint16_t *brightness_in_lumen);
+
+// Sets the device's brightness in Lumen.
+//
+// # Safety
+//
+// `device` must be a valid, open device.
+enum LitraStatus litra_set_brightness(const struct LitraDevice *device,
+                                      uint16_t brightness_in_lumen);
+
+// Returns the minimum brightness supported by the device in Lumen.
+//
+// # Safety
+//
+// `device` must be a valid, open device.
+uint16_t litra_minimum_brightness(const struct LitraDevice *device);
+
+// Returns the maximum brightness supported by the device in Lumen.
+//
+// # Safety
+//
+// `device` must be a valid, open device.
+uint16_t litra_maximum_brightness(const struct LitraDevice *device);
+
+// Queries the device's color temperature in Kelvin, writing the result to
+// `temperature_in_kelvin`.
+//
+// # Safety
+//
+// `device` must be a valid, open device and `temperature_in_kelvin` must point to writable
+// memory.
+enum LitraStatus litra_get_temperature(const struct LitraDevice *device,
+                                       uint16_t *temperature_in_kelvin);
+
+// Sets the device's color temperature in Kelvin.
+//
+// # Safety
+//
+// `device` must be a valid, open device.
+enum LitraStatus litra_set_temperature(const struct LitraDevice *device,
+                                       uint16_t temperature_in_kelvin);
+
+// Returns the minimum color temperature supported by the device in Kelvin.
+//
+// # Safety
+//
+// `device` must be a valid, open device.
+uint16_t litra_minimum_temperature(const struct LitraDevice *device);
+
+// Returns the maximum color temperature supported by the device in Kelvin.
+//
+// # Safety
+//
+// `device` must be a valid, open device.
+uint16_t litra_maximum_temperature(const struct LitraDevice *device);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LITRA_H */
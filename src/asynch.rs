@@ -0,0 +1,161 @@
+//! An async interface for controlling devices from a [tokio](https://tokio.rs) runtime.
+//!
+//! This module is only available with the `tokio` feature. Talking to a device blocks while
+//! waiting for it to respond, which can take tens of milliseconds, so [`AsyncDeviceHandle`] runs
+//! each operation on tokio's blocking thread pool rather than on the runtime's worker threads.
+//!
+//! ```no_run
+//! # async fn example() -> litra::DeviceResult<()> {
+//! use litra::Litra;
+//!
+//! let context = Litra::new()?;
+//! let device = context.get_connected_devices().next().expect("No device found");
+//! let device_handle = device.open(&context)?.into_async();
+//!
+//! device_handle.set_on(true).await?;
+//! println!("Brightness: {} lm", device_handle.brightness_in_lumen().await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{Capabilities, DeviceHandle, DeviceResult, DeviceType, FirmwareVersion};
+use std::panic;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The handle of an opened device, with async versions of the methods of [`DeviceHandle`] which
+/// talk to the device.
+///
+/// Cloning the handle is cheap, and the clones share the same device. Operations from different
+/// clones are run one at a time.
+#[derive(Debug, Clone)]
+pub struct AsyncDeviceHandle {
+    device_handle: Arc<Mutex<DeviceHandle>>,
+    capabilities: Capabilities,
+}
+
+impl DeviceHandle {
+    /// Converts the handle into an [`AsyncDeviceHandle`], for use from a tokio runtime.
+    #[must_use]
+    pub fn into_async(self) -> AsyncDeviceHandle {
+        AsyncDeviceHandle::from(self)
+    }
+}
+
+impl From<DeviceHandle> for AsyncDeviceHandle {
+    fn from(device_handle: DeviceHandle) -> Self {
+        AsyncDeviceHandle {
+            capabilities: device_handle.capabilities(),
+            device_handle: Arc::new(Mutex::new(device_handle)),
+        }
+    }
+}
+
+impl AsyncDeviceHandle {
+    /// Runs a blocking operation on the underlying [`DeviceHandle`] on tokio's blocking thread
+    /// pool, for example to call methods which don't have an async version.
+    pub async fn with_device_handle<T, F>(&self, operation: F) -> DeviceResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&DeviceHandle) -> DeviceResult<T> + Send + 'static,
+    {
+        let device_handle = Arc::clone(&self.device_handle);
+        let result = tokio::task::spawn_blocking(move || {
+            let device_handle = device_handle.lock().unwrap_or_else(PoisonError::into_inner);
+            operation(&device_handle)
+        })
+        .await;
+
+        match result {
+            Ok(result) => result,
+            Err(error) => panic::resume_unwind(error.into_panic()),
+        }
+    }
+
+    /// The model of the device.
+    #[must_use]
+    pub fn device_type(&self) -> DeviceType {
+        self.capabilities.device_type
+    }
+
+    /// Returns what the device supports.
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    /// Returns the minimum brightness supported by the device in Lumen.
+    #[must_use]
+    pub fn minimum_brightness_in_lumen(&self) -> u16 {
+        self.capabilities.minimum_brightness_in_lumen
+    }
+
+    /// Returns the maximum brightness supported by the device in Lumen.
+    #[must_use]
+    pub fn maximum_brightness_in_lumen(&self) -> u16 {
+        self.capabilities.maximum_brightness_in_lumen
+    }
+
+    /// Returns the minimum color temperature supported by the device in Kelvin.
+    #[must_use]
+    pub fn minimum_temperature_in_kelvin(&self) -> u16 {
+        self.capabilities.minimum_temperature_in_kelvin
+    }
+
+    /// Returns the maximum color temperature supported by the device in Kelvin.
+    #[must_use]
+    pub fn maximum_temperature_in_kelvin(&self) -> u16 {
+        self.capabilities.maximum_temperature_in_kelvin
+    }
+
+    /// Returns the serial number of the device.
+    pub async fn serial_number(&self) -> DeviceResult<Option<String>> {
+        self.with_device_handle(DeviceHandle::serial_number).await
+    }
+
+    /// Queries the current power status of the device. Returns `true` if the device is currently on.
+    pub async fn is_on(&self) -> DeviceResult<bool> {
+        self.with_device_handle(DeviceHandle::is_on).await
+    }
+
+    /// Sets the power status of the device. Turns the device on if `true` is passed and turns it
+    /// of on `false`.
+    pub async fn set_on(&self, on: bool) -> DeviceResult<()> {
+        self.with_device_handle(move |device_handle| device_handle.set_on(on))
+            .await
+    }
+
+    /// Queries the device's current brightness in Lumen.
+    pub async fn brightness_in_lumen(&self) -> DeviceResult<u16> {
+        self.with_device_handle(DeviceHandle::brightness_in_lumen)
+            .await
+    }
+
+    /// Sets the device's brightness in Lumen.
+    pub async fn set_brightness_in_lumen(&self, brightness_in_lumen: u16) -> DeviceResult<()> {
+        self.with_device_handle(move |device_handle| {
+            device_handle.set_brightness_in_lumen(brightness_in_lumen)
+        })
+        .await
+    }
+
+    /// Queries the device's current color temperature in Kelvin.
+    pub async fn temperature_in_kelvin(&self) -> DeviceResult<u16> {
+        self.with_device_handle(DeviceHandle::temperature_in_kelvin)
+            .await
+    }
+
+    /// Sets the device's color temperature in Kelvin.
+    pub async fn set_temperature_in_kelvin(&self, temperature_in_kelvin: u16) -> DeviceResult<()> {
+        self.with_device_handle(move |device_handle| {
+            device_handle.set_temperature_in_kelvin(temperature_in_kelvin)
+        })
+        .await
+    }
+
+    /// Queries the device's firmware version. The result is cached for the lifetime of the
+    /// handle.
+    pub async fn firmware_version(&self) -> DeviceResult<FirmwareVersion> {
+        self.with_device_handle(DeviceHandle::firmware_version)
+            .await
+    }
+}
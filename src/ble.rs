@@ -0,0 +1,292 @@
+//! Bluetooth LE support for the Litra Beam and Litra Beam LX, using [btleplug].
+//!
+//! This module is only available with the `ble` feature, which makes [`Litra`] list Bluetooth
+//! devices alongside USB ones. Devices are recognized by the name they advertise, and are
+//! controlled with the same HID++ messages as over USB, sent through Logitech's vendor-specific
+//! HID++ GATT service. Logitech doesn't document this service, so Bluetooth support is
+//! experimental.
+//!
+//! btleplug is async, so the context runs a small tokio runtime in the background. Operating
+//! systems only report devices they have already seen, so devices which aren't paired may need to
+//! be found with [`Litra::scan_bluetooth_devices`] first.
+//!
+//! [btleplug]: https://github.com/deviceplug/btleplug
+
+use crate::{
+    DeviceDescriptor, DeviceError, DeviceHandle, DeviceResult, DeviceType, Litra, Transport,
+    LONG_REPORT_ID,
+};
+use btleplug::api::bleuuid::uuid_from_u16;
+use btleplug::api::{
+    Central, Characteristic, Manager as _, Peripheral as _, ScanFilter, WriteType,
+};
+use btleplug::platform::{Adapter, Manager, Peripheral};
+use futures::StreamExt;
+use std::ffi::{CStr, CString};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// Logitech's HID++ GATT service, and the characteristic HID++ messages are written to and
+/// notified on. Messages are sent without the report ID and device index used over USB.
+const HIDPP_SERVICE_UUID: u128 = 0x0001_0000_0000_1000_8000_011f_2000_046d;
+const HIDPP_CHARACTERISTIC_UUID: u128 = 0x0001_0001_0000_1000_8000_011f_2000_046d;
+/// The standard Device Information service's Serial Number String characteristic.
+const SERIAL_NUMBER_CHARACTERISTIC_UUID: u16 = 0x2a25;
+
+/// The names advertised by supported devices. Longer names come first, since they start with the
+/// shorter ones.
+const DEVICE_NAMES: [(&str, DeviceType); 2] = [
+    ("Litra Beam LX", DeviceType::LitraBeamLX),
+    ("Litra Beam", DeviceType::LitraBeam),
+];
+
+/// The Bluetooth devices known to a [`Litra`] context.
+#[derive(Debug, Default)]
+pub(crate) struct Bluetooth {
+    connection: Option<Connection>,
+    devices: Vec<BluetoothDevice>,
+}
+
+#[derive(Debug)]
+struct Connection {
+    runtime: Arc<Runtime>,
+    adapter: Adapter,
+}
+
+impl Bluetooth {
+    pub(crate) fn devices(&self) -> &[BluetoothDevice] {
+        &self.devices
+    }
+
+    /// Refreshes the list of supported devices which the operating system knows about, scanning
+    /// for new devices first if `scan_duration` is given. If there is no Bluetooth adapter, no
+    /// devices are listed.
+    pub(crate) fn refresh(&mut self, scan_duration: Option<Duration>) -> DeviceResult<()> {
+        if self.connection.is_none() {
+            self.connection = Connection::new();
+        }
+        let Some(connection) = &self.connection else {
+            self.devices.clear();
+            return Ok(());
+        };
+
+        let runtime = Arc::clone(&connection.runtime);
+        let adapter = &connection.adapter;
+        self.devices = runtime.block_on(async {
+            if let Some(scan_duration) = scan_duration {
+                adapter
+                    .start_scan(ScanFilter::default())
+                    .await
+                    .map_err(bluetooth_error)?;
+                tokio::time::sleep(scan_duration).await;
+                adapter.stop_scan().await.map_err(bluetooth_error)?;
+            }
+
+            let mut devices = Vec::new();
+            for peripheral in adapter.peripherals().await.map_err(bluetooth_error)? {
+                let Some(properties) = peripheral.properties().await.map_err(bluetooth_error)?
+                else {
+                    continue;
+                };
+                let Some(device_type) = properties.local_name.as_deref().and_then(device_type)
+                else {
+                    continue;
+                };
+                // Reading the serial number needs a connection, so it's only known for devices
+                // which are already connected
+                let serial_number = if peripheral.is_connected().await.unwrap_or(false) {
+                    read_serial_number(&peripheral).await
+                } else {
+                    None
+                };
+                devices.push(BluetoothDevice {
+                    runtime: Arc::clone(&runtime),
+                    path: CString::new(format!("bluetooth:{}", peripheral.id()))
+                        .unwrap_or_default(),
+                    serial_number,
+                    descriptor: DeviceDescriptor::for_device_type(device_type),
+                    peripheral,
+                });
+            }
+            Ok::<_, DeviceError>(devices)
+        })?;
+        Ok(())
+    }
+}
+
+impl Connection {
+    /// Connects to the first Bluetooth adapter, returning `None` if there isn't one or Bluetooth
+    /// isn't available.
+    fn new() -> Option<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .ok()?;
+        let adapter = runtime.block_on(async {
+            let manager = Manager::new().await.ok()?;
+            manager.adapters().await.ok()?.into_iter().next()
+        })?;
+        Some(Connection {
+            runtime: Arc::new(runtime),
+            adapter,
+        })
+    }
+}
+
+/// A supported device known to the Bluetooth adapter.
+#[derive(Debug)]
+pub(crate) struct BluetoothDevice {
+    runtime: Arc<Runtime>,
+    peripheral: Peripheral,
+    path: CString,
+    serial_number: Option<String>,
+    descriptor: DeviceDescriptor,
+}
+
+impl BluetoothDevice {
+    pub(crate) fn path(&self) -> &CStr {
+        &self.path
+    }
+
+    pub(crate) fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    pub(crate) fn descriptor(&self) -> DeviceDescriptor {
+        self.descriptor
+    }
+
+    /// Connects to the device, if it isn't already connected, and subscribes to its HID++
+    /// responses.
+    pub(crate) fn open(&self, descriptor: DeviceDescriptor) -> DeviceResult<DeviceHandle> {
+        let peripheral = self.peripheral.clone();
+        let (sender, responses) = mpsc::channel();
+        let (characteristic, serial_number) = self.runtime.block_on(async {
+            if !peripheral.is_connected().await.map_err(bluetooth_error)? {
+                peripheral.connect().await.map_err(bluetooth_error)?;
+            }
+            peripheral
+                .discover_services()
+                .await
+                .map_err(bluetooth_error)?;
+            let characteristic = peripheral
+                .characteristics()
+                .into_iter()
+                .find(|characteristic| {
+                    characteristic.service_uuid.as_u128() == HIDPP_SERVICE_UUID
+                        && characteristic.uuid.as_u128() == HIDPP_CHARACTERISTIC_UUID
+                })
+                .ok_or(DeviceError::Unsupported)?;
+
+            peripheral
+                .subscribe(&characteristic)
+                .await
+                .map_err(bluetooth_error)?;
+            let mut notifications = peripheral.notifications().await.map_err(bluetooth_error)?;
+            self.runtime.spawn(async move {
+                while let Some(notification) = notifications.next().await {
+                    if notification.uuid.as_u128() == HIDPP_CHARACTERISTIC_UUID
+                        && sender.send(notification.value).is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+
+            let serial_number = match &self.serial_number {
+                Some(serial_number) => Some(serial_number.clone()),
+                None => read_serial_number(&peripheral).await,
+            };
+            Ok::<_, DeviceError>((characteristic, serial_number))
+        })?;
+
+        Ok(DeviceHandle::from_transport(
+            BluetoothTransport {
+                runtime: Arc::clone(&self.runtime),
+                peripheral,
+                characteristic,
+                serial_number,
+                responses,
+            },
+            descriptor,
+        ))
+    }
+}
+
+/// A [`Transport`] which exchanges HID++ messages with a device over Bluetooth LE.
+#[derive(Debug)]
+struct BluetoothTransport {
+    runtime: Arc<Runtime>,
+    peripheral: Peripheral,
+    characteristic: Characteristic,
+    serial_number: Option<String>,
+    responses: mpsc::Receiver<Vec<u8>>,
+}
+
+impl Transport for BluetoothTransport {
+    fn write(&self, report: &[u8]) -> DeviceResult<()> {
+        // Skip the report ID and device index, which aren't used over Bluetooth
+        let message = report.get(2..).unwrap_or_default();
+        self.runtime
+            .block_on(
+                self.peripheral
+                    .write(&self.characteristic, message, WriteType::WithResponse),
+            )
+            .map_err(bluetooth_error)
+    }
+
+    fn read_timeout(&self, report: &mut [u8], timeout: Duration) -> DeviceResult<usize> {
+        let message = match self.responses.recv_timeout(timeout) {
+            Ok(message) => message,
+            Err(mpsc::RecvTimeoutError::Timeout) => return Ok(0),
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err(DeviceError::NotConnected),
+        };
+
+        // Add back the report ID and device index, so responses look like they do over USB
+        let mut response = vec![LONG_REPORT_ID, 0xff];
+        response.extend_from_slice(&message);
+        let length = report.len().min(response.len());
+        report[..length].copy_from_slice(&response[..length]);
+        Ok(length)
+    }
+
+    fn serial_number(&self) -> DeviceResult<Option<String>> {
+        Ok(self.serial_number.clone())
+    }
+}
+
+impl Litra {
+    /// Scans for Bluetooth devices for `scan_duration`, then refreshes the list of connected
+    /// devices returned by [`Litra::get_connected_devices`]. This is only needed for devices
+    /// which the operating system hasn't seen yet, e.g. ones which aren't paired.
+    pub fn scan_bluetooth_devices(&mut self, scan_duration: Duration) -> DeviceResult<()> {
+        self.bluetooth.refresh(Some(scan_duration))
+    }
+}
+
+/// Returns the model of a device from the name it advertises.
+fn device_type(local_name: &str) -> Option<DeviceType> {
+    DEVICE_NAMES
+        .iter()
+        .find(|(name, _)| local_name.starts_with(name))
+        .map(|(_, device_type)| *device_type)
+}
+
+/// Reads a device's serial number from the standard Device Information service, if it has one.
+async fn read_serial_number(peripheral: &Peripheral) -> Option<String> {
+    peripheral.discover_services().await.ok()?;
+    let characteristic = peripheral
+        .characteristics()
+        .into_iter()
+        .find(|characteristic| {
+            characteristic.uuid == uuid_from_u16(SERIAL_NUMBER_CHARACTERISTIC_UUID)
+        })?;
+    let serial_number = peripheral.read(&characteristic).await.ok()?;
+    Some(String::from_utf8_lossy(&serial_number).trim().to_string())
+}
+
+fn bluetooth_error(error: btleplug::Error) -> DeviceError {
+    DeviceError::TransportError(Box::new(error))
+}
@@ -0,0 +1,434 @@
+//! C API for controlling Logitech Litra lights from C, C++ and other languages with a C FFI.
+//!
+//! This module is only available with the `ffi` feature. To build a shared library, run:
+//!
+//! ```sh
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! ```
+//!
+//! The matching header is checked in at `include/litra.h`, and can be regenerated with
+//! [`cbindgen`](https://github.com/mozilla/cbindgen) using the `cbindgen.toml` in the root of the
+//! repository.
+
+#![allow(unsafe_code)]
+
+use crate::{DeviceError, DeviceHandle, DeviceType, Litra};
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+/// The maximum length of a serial number returned by [`litra_list_devices`], including the
+/// terminating NUL byte.
+pub const LITRA_SERIAL_NUMBER_LENGTH: usize = 64;
+
+/// The result of a C API call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LitraStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// A required pointer was `NULL` or a string wasn't valid UTF-8.
+    InvalidArgument = 1,
+    /// No matching device was found.
+    DeviceNotFound = 2,
+    /// The device is not supported.
+    Unsupported = 3,
+    /// The brightness is outside of the range supported by the device.
+    InvalidBrightness = 4,
+    /// The temperature is outside of the range supported by the device, or isn't a multiple
+    /// of the device's temperature step.
+    InvalidTemperature = 5,
+    /// Communicating with the device failed.
+    HidError = 6,
+    /// The device didn't respond in time.
+    Timeout = 7,
+}
+
+impl From<&DeviceError> for LitraStatus {
+    fn from(error: &DeviceError) -> Self {
+        match error {
+            DeviceError::Unsupported => LitraStatus::Unsupported,
+            DeviceError::InvalidBrightness(_) => LitraStatus::InvalidBrightness,
+            DeviceError::InvalidTemperature(_) => LitraStatus::InvalidTemperature,
+            DeviceError::HidError(_) => LitraStatus::HidError,
+            DeviceError::InvalidRawRequest(_) | DeviceError::InvalidDeviceDescriptor(_) => {
+                LitraStatus::InvalidArgument
+            }
+            DeviceError::Timeout => LitraStatus::Timeout,
+            DeviceError::NotConnected => LitraStatus::DeviceNotFound,
+            DeviceError::TransportError(_) => LitraStatus::HidError,
+        }
+    }
+}
+
+/// The model of a device.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LitraDeviceType {
+    /// Logitech Litra Glow.
+    LitraGlow = 0,
+    /// Logitech Litra Beam.
+    LitraBeam = 1,
+    /// Logitech Litra Beam LX.
+    LitraBeamLX = 2,
+}
+
+impl From<DeviceType> for LitraDeviceType {
+    fn from(device_type: DeviceType) -> Self {
+        match device_type {
+            DeviceType::LitraGlow => LitraDeviceType::LitraGlow,
+            DeviceType::LitraBeam => LitraDeviceType::LitraBeam,
+            DeviceType::LitraBeamLX => LitraDeviceType::LitraBeamLX,
+        }
+    }
+}
+
+/// A connected device, as returned by [`litra_list_devices`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LitraDeviceInfo {
+    /// The model of the device.
+    pub device_type: LitraDeviceType,
+    /// The NUL-terminated serial number of the device. This is empty if the device doesn't report
+    /// a serial number.
+    pub serial_number: [c_char; LITRA_SERIAL_NUMBER_LENGTH],
+}
+
+/// An opaque Litra context, created with [`litra_context_new`].
+#[derive(Debug)]
+pub struct LitraContext(Litra);
+
+/// An opaque handle to an opened device, created with [`litra_device_open`] or
+/// [`litra_device_open_by_serial_number`].
+#[derive(Debug)]
+pub struct LitraDevice(DeviceHandle);
+
+/// Creates a new Litra context. Returns `NULL` if initialization failed. The context must be
+/// freed with [`litra_context_free`].
+#[no_mangle]
+pub extern "C" fn litra_context_new() -> *mut LitraContext {
+    match Litra::new() {
+        Ok(context) => Box::into_raw(Box::new(LitraContext(context))),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Frees a context created with [`litra_context_new`]. Passing `NULL` is a no-op.
+///
+/// # Safety
+///
+/// `context` must be `NULL` or a pointer returned by [`litra_context_new`] which hasn't already
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn litra_context_free(context: *mut LitraContext) {
+    if !context.is_null() {
+        drop(Box::from_raw(context));
+    }
+}
+
+/// Refreshes the list of connected devices.
+///
+/// # Safety
+///
+/// `context` must be a valid pointer returned by [`litra_context_new`].
+#[no_mangle]
+pub unsafe extern "C" fn litra_refresh_devices(context: *mut LitraContext) -> LitraStatus {
+    let Some(context) = context.as_mut() else {
+        return LitraStatus::InvalidArgument;
+    };
+    status_from_result(context.0.refresh_connected_devices())
+}
+
+/// Writes up to `capacity` connected devices into `devices`, and returns the total number of
+/// connected devices. Call with `capacity` set to `0` to find out how much space is needed.
+///
+/// # Safety
+///
+/// `context` must be a valid pointer returned by [`litra_context_new`]. `devices` must point to at
+/// least `capacity` writable [`LitraDeviceInfo`] values, and may only be `NULL` if `capacity`
+/// is `0`.
+#[no_mangle]
+pub unsafe extern "C" fn litra_list_devices(
+    context: *const LitraContext,
+    devices: *mut LitraDeviceInfo,
+    capacity: usize,
+) -> usize {
+    let Some(context) = context.as_ref() else {
+        return 0;
+    };
+
+    let mut count = 0;
+    for device in context.0.get_connected_devices() {
+        if count < capacity && !devices.is_null() {
+            let mut serial_number = [0; LITRA_SERIAL_NUMBER_LENGTH];
+            let bytes = device.serial_number().unwrap_or("").as_bytes();
+            for (target, byte) in serial_number
+                .iter_mut()
+                .zip(bytes.iter().take(LITRA_SERIAL_NUMBER_LENGTH - 1))
+            {
+                *target = *byte as c_char;
+            }
+
+            devices.add(count).write(LitraDeviceInfo {
+                device_type: device.device_type().into(),
+                serial_number,
+            });
+        }
+        count += 1;
+    }
+    count
+}
+
+/// Opens the connected device at `index` in the list returned by [`litra_list_devices`]. The
+/// device must be closed with [`litra_device_close`].
+///
+/// # Safety
+///
+/// `context` must be a valid pointer returned by [`litra_context_new`], and `device` must point
+/// to writable memory for a device pointer.
+#[no_mangle]
+pub unsafe extern "C" fn litra_device_open(
+    context: *const LitraContext,
+    index: usize,
+    device: *mut *mut LitraDevice,
+) -> LitraStatus {
+    let Some(context) = context.as_ref() else {
+        return LitraStatus::InvalidArgument;
+    };
+    if device.is_null() {
+        return LitraStatus::InvalidArgument;
+    }
+
+    match context.0.get_connected_devices().nth(index) {
+        Some(found) => open_into(found.open(&context.0), device),
+        None => LitraStatus::DeviceNotFound,
+    }
+}
+
+/// Opens the connected device with the given NUL-terminated serial number. The device must be
+/// closed with [`litra_device_close`].
+///
+/// # Safety
+///
+/// `context` must be a valid pointer returned by [`litra_context_new`], `serial_number` must be a
+/// valid NUL-terminated string, and `device` must point to writable memory for a device pointer.
+#[no_mangle]
+pub unsafe extern "C" fn litra_device_open_by_serial_number(
+    context: *const LitraContext,
+    serial_number: *const c_char,
+    device: *mut *mut LitraDevice,
+) -> LitraStatus {
+    let Some(context) = context.as_ref() else {
+        return LitraStatus::InvalidArgument;
+    };
+    if serial_number.is_null() || device.is_null() {
+        return LitraStatus::InvalidArgument;
+    }
+    let Ok(serial_number) = CStr::from_ptr(serial_number).to_str() else {
+        return LitraStatus::InvalidArgument;
+    };
+
+    match context
+        .0
+        .get_connected_devices()
+        .find(|found| found.serial_number() == Some(serial_number))
+    {
+        Some(found) => open_into(found.open(&context.0), device),
+        None => LitraStatus::DeviceNotFound,
+    }
+}
+
+/// Closes a device opened with [`litra_device_open`] or [`litra_device_open_by_serial_number`].
+/// Passing `NULL` is a no-op.
+///
+/// # Safety
+///
+/// `device` must be `NULL` or a pointer returned by one of the open functions which hasn't already
+/// been closed.
+#[no_mangle]
+pub unsafe extern "C" fn litra_device_close(device: *mut LitraDevice) {
+    if !device.is_null() {
+        drop(Box::from_raw(device));
+    }
+}
+
+/// Returns the model of the device.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device.
+#[no_mangle]
+pub unsafe extern "C" fn litra_device_type(device: *const LitraDevice) -> LitraDeviceType {
+    (*device).0.device_type().into()
+}
+
+/// Queries whether the device is on, writing the result to `is_on`.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device and `is_on` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn litra_is_on(device: *const LitraDevice, is_on: *mut bool) -> LitraStatus {
+    query(device, is_on, DeviceHandle::is_on)
+}
+
+/// Turns the device on or off.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device.
+#[no_mangle]
+pub unsafe extern "C" fn litra_set_on(device: *const LitraDevice, on: bool) -> LitraStatus {
+    let Some(device) = device.as_ref() else {
+        return LitraStatus::InvalidArgument;
+    };
+    status_from_result(device.0.set_on(on))
+}
+
+/// Queries the device's brightness in Lumen, writing the result to `brightness_in_lumen`.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device and `brightness_in_lumen` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn litra_get_brightness(
+    device: *const LitraDevice,
+    brightness_in_lumen: *mut u16,
+) -> LitraStatus {
+    query(
+        device,
+        brightness_in_lumen,
+        DeviceHandle::brightness_in_lumen,
+    )
+}
+
+/// Sets the device's brightness in Lumen.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device.
+#[no_mangle]
+pub unsafe extern "C" fn litra_set_brightness(
+    device: *const LitraDevice,
+    brightness_in_lumen: u16,
+) -> LitraStatus {
+    let Some(device) = device.as_ref() else {
+        return LitraStatus::InvalidArgument;
+    };
+    status_from_result(device.0.set_brightness_in_lumen(brightness_in_lumen))
+}
+
+/// Returns the minimum brightness supported by the device in Lumen.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device.
+#[no_mangle]
+pub unsafe extern "C" fn litra_minimum_brightness(device: *const LitraDevice) -> u16 {
+    (*device).0.minimum_brightness_in_lumen()
+}
+
+/// Returns the maximum brightness supported by the device in Lumen.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device.
+#[no_mangle]
+pub unsafe extern "C" fn litra_maximum_brightness(device: *const LitraDevice) -> u16 {
+    (*device).0.maximum_brightness_in_lumen()
+}
+
+/// Queries the device's color temperature in Kelvin, writing the result to
+/// `temperature_in_kelvin`.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device and `temperature_in_kelvin` must point to writable
+/// memory.
+#[no_mangle]
+pub unsafe extern "C" fn litra_get_temperature(
+    device: *const LitraDevice,
+    temperature_in_kelvin: *mut u16,
+) -> LitraStatus {
+    query(
+        device,
+        temperature_in_kelvin,
+        DeviceHandle::temperature_in_kelvin,
+    )
+}
+
+/// Sets the device's color temperature in Kelvin.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device.
+#[no_mangle]
+pub unsafe extern "C" fn litra_set_temperature(
+    device: *const LitraDevice,
+    temperature_in_kelvin: u16,
+) -> LitraStatus {
+    let Some(device) = device.as_ref() else {
+        return LitraStatus::InvalidArgument;
+    };
+    status_from_result(device.0.set_temperature_in_kelvin(temperature_in_kelvin))
+}
+
+/// Returns the minimum color temperature supported by the device in Kelvin.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device.
+#[no_mangle]
+pub unsafe extern "C" fn litra_minimum_temperature(device: *const LitraDevice) -> u16 {
+    (*device).0.minimum_temperature_in_kelvin()
+}
+
+/// Returns the maximum color temperature supported by the device in Kelvin.
+///
+/// # Safety
+///
+/// `device` must be a valid, open device.
+#[no_mangle]
+pub unsafe extern "C" fn litra_maximum_temperature(device: *const LitraDevice) -> u16 {
+    (*device).0.maximum_temperature_in_kelvin()
+}
+
+fn status_from_result(result: Result<(), DeviceError>) -> LitraStatus {
+    match result {
+        Ok(()) => LitraStatus::Ok,
+        Err(error) => LitraStatus::from(&error),
+    }
+}
+
+unsafe fn open_into(
+    result: Result<DeviceHandle, DeviceError>,
+    device: *mut *mut LitraDevice,
+) -> LitraStatus {
+    match result {
+        Ok(handle) => {
+            device.write(Box::into_raw(Box::new(LitraDevice(handle))));
+            LitraStatus::Ok
+        }
+        Err(error) => LitraStatus::from(&error),
+    }
+}
+
+unsafe fn query<T>(
+    device: *const LitraDevice,
+    out: *mut T,
+    operation: impl Fn(&DeviceHandle) -> Result<T, DeviceError>,
+) -> LitraStatus {
+    let Some(device) = device.as_ref() else {
+        return LitraStatus::InvalidArgument;
+    };
+    if out.is_null() {
+        return LitraStatus::InvalidArgument;
+    }
+
+    match operation(&device.0) {
+        Ok(value) => {
+            out.write(value);
+            LitraStatus::Ok
+        }
+        Err(error) => LitraStatus::from(&error),
+    }
+}
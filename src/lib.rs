@@ -31,47 +31,205 @@
 #![cfg_attr(not(test), deny(clippy::panic_in_result_fn))]
 #![cfg_attr(not(debug_assertions), deny(clippy::used_underscore_binding))]
 
+#[cfg(feature = "tokio")]
+pub mod asynch;
+#[cfg(feature = "ble")]
+pub mod ble;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(feature = "node")]
+pub mod node;
+#[cfg(feature = "wasm")]
+pub mod webhid;
+
+/// The [`hidapi`] crate, re-exported so that applications can use the same version, e.g. to create
+/// a context with [`Litra::with_hidapi`]. The backend it uses can be chosen with this crate's
+/// features. hidapi isn't available on WebAssembly, where devices are reached with the `wasm`
+/// feature instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub use hidapi;
+
+#[cfg(not(target_arch = "wasm32"))]
 use hidapi::{DeviceInfo, HidApi, HidDevice, HidError};
+use std::cell::{Cell, OnceCell};
+use std::collections::BTreeSet;
 use std::error::Error;
+#[cfg(not(target_arch = "wasm32"))]
+use std::ffi::{CStr, CString};
 use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
 
 /// Litra context.
 ///
 /// This can be used to list available devices.
-pub struct Litra(HidApi);
+#[cfg(not(target_arch = "wasm32"))]
+pub struct Litra {
+    hidapi: HidApi,
+    #[cfg(feature = "ble")]
+    bluetooth: ble::Bluetooth,
+    #[cfg(feature = "mock")]
+    simulated_devices: Vec<mock::MockDevice>,
+    device_descriptors: Vec<DeviceDescriptor>,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
+}
 
+#[cfg(not(target_arch = "wasm32"))]
 impl fmt::Debug for Litra {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_tuple("Litra").finish()
+        f.debug_struct("Litra")
+            .field("device_descriptors", &self.device_descriptors)
+            .field("timeout", &self.timeout)
+            .field("retry_policy", &self.retry_policy)
+            .finish_non_exhaustive()
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Litra {
     /// Initialize a new Litra context.
+    ///
+    /// With the `ble` feature, this also lists the Bluetooth devices which the operating system
+    /// knows about.
     pub fn new() -> DeviceResult<Self> {
-        let hidapi = HidApi::new()?;
+        #[cfg_attr(not(feature = "ble"), allow(unused_mut))]
+        let mut context = Litra::with_hidapi(HidApi::new()?);
+        #[cfg(feature = "ble")]
+        context.bluetooth.refresh(None)?;
+        Ok(context)
+    }
+
+    /// Initialize a new Litra context using an existing hidapi context.
+    ///
+    /// hidapi only allows one context per process, so applications which already use hidapi for
+    /// other devices should create their context with this. It can still be used for other devices
+    /// with [`Litra::hidapi`], and taken back with [`Litra::into_hidapi`].
+    ///
+    /// On macOS, devices are opened in non-exclusive mode so that other applications can use them
+    /// at the same time, which also applies to devices opened with the hidapi context afterwards.
+    #[must_use]
+    pub fn with_hidapi(hidapi: HidApi) -> Self {
         #[cfg(target_os = "macos")]
         hidapi.set_open_exclusive(false);
-        Ok(Litra(hidapi))
+        Litra {
+            hidapi,
+            #[cfg(feature = "ble")]
+            bluetooth: ble::Bluetooth::default(),
+            #[cfg(feature = "mock")]
+            simulated_devices: Vec::new(),
+            device_descriptors: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets how long devices opened from this context will wait for a response before failing
+    /// with [`DeviceError::Timeout`]. Defaults to 1 second.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets how devices opened from this context will retry operations which fail with an error
+    /// which may be temporary. By default, operations aren't retried.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Registers an additional device which isn't supported out of the box, e.g. a new model or a
+    /// regional variant with a different product ID. Registered descriptors take precedence over
+    /// the built-in ones, so this can also be used to override how a supported device is used.
+    ///
+    /// Devices are matched when listed, so this should be called before
+    /// [`Litra::get_connected_devices`].
+    ///
+    /// Fails with [`DeviceError::InvalidDeviceDescriptor`] if the descriptor's minimum brightness
+    /// or temperature is greater than its maximum, in which case it isn't registered.
+    pub fn register_device_descriptor(
+        &mut self,
+        device_descriptor: DeviceDescriptor,
+    ) -> DeviceResult<()> {
+        device_descriptor.validate()?;
+        self.device_descriptors
+            .retain(|existing| existing.product_id != device_descriptor.product_id);
+        self.device_descriptors.push(device_descriptor);
+        Ok(())
     }
 
     /// Returns an [`Iterator`] of cached connected devices supported by this library. To refresh the list of connected devices, use [`Litra::refresh_connected_devices`].
+    ///
+    /// With the `ble` feature, this includes devices connected over Bluetooth, and with the `mock`
+    /// feature, it includes any simulated devices added with [`Litra::add_simulated_device`].
     pub fn get_connected_devices(&self) -> impl Iterator<Item = Device<'_>> {
-        self.0
+        let devices = self
+            .hidapi
             .device_list()
-            .filter_map(|device_info| Device::try_from(device_info).ok())
+            .filter_map(|device_info| self.device_from_info(device_info));
+        #[cfg(feature = "ble")]
+        let devices = devices.chain(self.bluetooth.devices().iter().map(|device| Device {
+            source: DeviceSource::Bluetooth(device),
+            descriptor: device.descriptor(),
+        }));
+        #[cfg(feature = "mock")]
+        let devices = devices.chain(
+            self.simulated_devices
+                .iter()
+                .filter(|device| device.is_connected())
+                .map(|device| Device {
+                    source: DeviceSource::Simulated(device),
+                    descriptor: device.descriptor(),
+                }),
+        );
+        devices
+    }
+
+    /// Returns an [`Iterator`] of cached connected Logitech devices which look like lights, since
+    /// they use the same HID usage page as supported devices, but aren't recognized by this
+    /// library. This is useful for diagnosing why a device isn't detected.
+    pub fn get_unrecognized_devices(&self) -> impl Iterator<Item = &DeviceInfo> {
+        self.hidapi.device_list().filter(|device_info| {
+            is_litra_interface(device_info) && self.device_from_info(device_info).is_none()
+        })
+    }
+
+    fn device_from_info<'a>(&self, device_info: &'a DeviceInfo) -> Option<Device<'a>> {
+        let registered_descriptor = self
+            .device_descriptors
+            .iter()
+            .find(|descriptor| descriptor.product_id == device_info.product_id());
+        match registered_descriptor {
+            Some(descriptor) if is_litra_interface(device_info) => Some(Device {
+                source: DeviceSource::Hid(device_info),
+                descriptor: *descriptor,
+            }),
+            _ => Device::try_from(device_info).ok(),
+        }
     }
 
     /// Refreshes the list of connected devices, returned by [`Litra::get_connected_devices`].
     pub fn refresh_connected_devices(&mut self) -> DeviceResult<()> {
-        self.0.refresh_devices()?;
+        self.hidapi.refresh_devices()?;
+        #[cfg(feature = "ble")]
+        self.bluetooth.refresh(None)?;
         Ok(())
     }
 
     /// Retrieve the underlying hidapi context.
     #[must_use]
     pub fn hidapi(&self) -> &HidApi {
-        &self.0
+        &self.hidapi
+    }
+
+    /// Consumes the context, returning the underlying hidapi context.
+    #[must_use]
+    pub fn into_hidapi(self) -> HidApi {
+        self.hidapi
     }
 }
 
@@ -92,6 +250,54 @@ pub enum DeviceType {
     LitraBeamLX,
 }
 
+// The maximum brightness of each model and the 2700-6500 K temperature range match Logitech's
+// published specifications for the Litra Glow, Beam and Beam LX. The minimum brightness values
+// haven't been published. These are the defaults for built-in `DeviceDescriptor`s, which can be
+// overridden per device.
+impl DeviceType {
+    /// The minimum brightness supported by this model in Lumen.
+    #[must_use]
+    pub fn minimum_brightness_in_lumen(&self) -> u16 {
+        match self {
+            DeviceType::LitraGlow => 20,
+            DeviceType::LitraBeam | DeviceType::LitraBeamLX => 30,
+        }
+    }
+
+    /// The maximum brightness supported by this model in Lumen.
+    #[must_use]
+    pub fn maximum_brightness_in_lumen(&self) -> u16 {
+        match self {
+            DeviceType::LitraGlow => 250,
+            DeviceType::LitraBeam | DeviceType::LitraBeamLX => 400,
+        }
+    }
+
+    /// The minimum color temperature supported by this model in Kelvin.
+    #[must_use]
+    pub fn minimum_temperature_in_kelvin(&self) -> u16 {
+        match self {
+            DeviceType::LitraGlow | DeviceType::LitraBeam | DeviceType::LitraBeamLX => 2700,
+        }
+    }
+
+    /// The maximum color temperature supported by this model in Kelvin.
+    #[must_use]
+    pub fn maximum_temperature_in_kelvin(&self) -> u16 {
+        match self {
+            DeviceType::LitraGlow | DeviceType::LitraBeam | DeviceType::LitraBeamLX => 6500,
+        }
+    }
+
+    /// The index of the HID++ illumination feature on this model.
+    fn feature_index(&self) -> u8 {
+        match self {
+            DeviceType::LitraGlow | DeviceType::LitraBeam => 0x04,
+            DeviceType::LitraBeamLX => 0x06,
+        }
+    }
+}
+
 impl fmt::Display for DeviceType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -103,7 +309,10 @@ impl fmt::Display for DeviceType {
 }
 
 /// A device-relatred error.
+///
+/// More variants may be added in minor releases, so matches on this need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum DeviceError {
     /// Tried to use a device that is not supported.
     Unsupported,
@@ -112,7 +321,18 @@ pub enum DeviceError {
     /// Tried to set an invalid temperature value.
     InvalidTemperature(u16),
     /// A [`hidapi`] operation failed.
+    #[cfg(not(target_arch = "wasm32"))]
     HidError(HidError),
+    /// Tried to send an invalid raw request with [`DeviceHandle::send_raw`].
+    InvalidRawRequest(&'static str),
+    /// Tried to register an invalid [`DeviceDescriptor`].
+    InvalidDeviceDescriptor(&'static str),
+    /// The device didn't respond in time.
+    Timeout,
+    /// Tried to open an [`OwnedDevice`] which is no longer connected.
+    NotConnected,
+    /// A [`Transport`] other than [`HidDevice`] failed to communicate with the device.
+    TransportError(Box<dyn Error + Send + Sync>),
 }
 
 impl fmt::Display for DeviceError {
@@ -125,21 +345,47 @@ impl fmt::Display for DeviceError {
             DeviceError::InvalidTemperature(value) => {
                 write!(f, "Temperature {} K is not supported", value)
             }
+            #[cfg(not(target_arch = "wasm32"))]
             DeviceError::HidError(error) => write!(f, "HID error occurred: {}", error),
+            DeviceError::InvalidRawRequest(reason) => {
+                write!(f, "Invalid raw request: {}", reason)
+            }
+            DeviceError::InvalidDeviceDescriptor(reason) => {
+                write!(f, "Invalid device descriptor: {}", reason)
+            }
+            DeviceError::Timeout => write!(f, "Timed out waiting for the device to respond"),
+            DeviceError::NotConnected => write!(f, "Device is no longer connected"),
+            DeviceError::TransportError(error) => {
+                write!(f, "Communicating with the device failed: {}", error)
+            }
+        }
+    }
+}
+
+impl DeviceError {
+    /// Returns whether the error may be temporary, so the operation may succeed if it's retried.
+    fn is_temporary(&self) -> bool {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            DeviceError::HidError(_) => true,
+            DeviceError::TransportError(_) | DeviceError::Timeout => true,
+            _ => false,
         }
     }
 }
 
 impl Error for DeviceError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        if let DeviceError::HidError(error) = self {
-            Some(error)
-        } else {
-            None
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            DeviceError::HidError(error) => Some(error),
+            DeviceError::TransportError(error) => Some(error.as_ref()),
+            _ => None,
         }
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl From<HidError> for DeviceError {
     fn from(error: HidError) -> Self {
         DeviceError::HidError(error)
@@ -150,361 +396,1257 @@ impl From<HidError> for DeviceError {
 pub type DeviceResult<T> = Result<T, DeviceError>;
 
 /// A device that can be used.
+#[cfg(not(target_arch = "wasm32"))]
 #[derive(Debug)]
 pub struct Device<'a> {
-    device_info: &'a DeviceInfo,
-    device_type: DeviceType,
+    source: DeviceSource<'a>,
+    descriptor: DeviceDescriptor,
 }
 
+/// Where a [`Device`] was found.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy)]
+enum DeviceSource<'a> {
+    Hid(&'a DeviceInfo),
+    #[cfg(feature = "ble")]
+    Bluetooth(&'a ble::BluetoothDevice),
+    #[cfg(feature = "mock")]
+    Simulated(&'a mock::MockDevice),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
 impl<'a> TryFrom<&'a DeviceInfo> for Device<'a> {
     type Error = DeviceError;
 
     fn try_from(device_info: &'a DeviceInfo) -> Result<Self, DeviceError> {
-        if device_info.vendor_id() != VENDOR_ID || device_info.usage_page() != USAGE_PAGE {
+        if !is_litra_interface(device_info) {
             return Err(DeviceError::Unsupported);
         }
-        device_type_from_product_id(device_info.product_id())
-            .map(|device_type| Device {
-                device_info,
-                device_type,
+        DeviceDescriptor::for_product_id(device_info.product_id())
+            .map(|descriptor| Device {
+                source: DeviceSource::Hid(device_info),
+                descriptor,
             })
             .ok_or(DeviceError::Unsupported)
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 impl Device<'_> {
-    /// The model of the device.
+    /// The hidapi information about the device, or `None` if it isn't a HID device, e.g. because
+    /// it is connected over Bluetooth or simulated.
+    #[must_use]
+    pub fn device_info(&self) -> Option<&DeviceInfo> {
+        match self.source {
+            DeviceSource::Hid(device_info) => Some(device_info),
+            #[cfg(feature = "ble")]
+            DeviceSource::Bluetooth(_) => None,
+            #[cfg(feature = "mock")]
+            DeviceSource::Simulated(_) => None,
+        }
+    }
+
+    /// The path of the device, which identifies it while it's connected. For HID devices, this is
+    /// the HID path, for Bluetooth devices, it is `bluetooth:` followed by the operating system's
+    /// identifier for the device, and for simulated devices, it is `simulated:` followed by the
+    /// serial number.
+    #[must_use]
+    pub fn path(&self) -> &CStr {
+        match self.source {
+            DeviceSource::Hid(device_info) => device_info.path(),
+            #[cfg(feature = "ble")]
+            DeviceSource::Bluetooth(device) => device.path(),
+            #[cfg(feature = "mock")]
+            DeviceSource::Simulated(device) => device.path(),
+        }
+    }
+
+    /// The serial number of the device, if it has one. For Bluetooth devices, this is only known
+    /// if the device was already connected when it was listed.
     #[must_use]
-    pub fn device_info(&self) -> &DeviceInfo {
-        self.device_info
+    pub fn serial_number(&self) -> Option<&str> {
+        match self.source {
+            DeviceSource::Hid(device_info) => device_info.serial_number(),
+            #[cfg(feature = "ble")]
+            DeviceSource::Bluetooth(device) => device.serial_number(),
+            #[cfg(feature = "mock")]
+            DeviceSource::Simulated(device) => Some(device.serial_number()),
+        }
     }
 
     /// The model of the device.
     #[must_use]
     pub fn device_type(&self) -> DeviceType {
-        self.device_type
+        self.descriptor.device_type
+    }
+
+    /// The descriptor used to communicate with the device.
+    #[must_use]
+    pub fn descriptor(&self) -> &DeviceDescriptor {
+        &self.descriptor
     }
 
     /// Opens the device and returns a [`DeviceHandle`] that can be used for getting and setting the
     /// device status. On macOS, this will open the device in non-exclusive mode.
+    ///
+    /// The HID++ report format used to talk to the device is detected from its report descriptor
+    /// where possible. If the descriptor can't be read, long reports are used, falling back to
+    /// short reports if a write fails.
     pub fn open(&self, context: &Litra) -> DeviceResult<DeviceHandle> {
-        let hid_device = self.device_info.open_device(context.hidapi())?;
-        Ok(DeviceHandle {
-            hid_device,
-            device_type: self.device_type,
-        })
+        let mut device_handle = match self.source {
+            DeviceSource::Hid(device_info) => {
+                let hid_device = device_info.open_device(context.hidapi())?;
+                DeviceHandle::from_transport(hid_device, self.descriptor)
+            }
+            #[cfg(feature = "ble")]
+            DeviceSource::Bluetooth(device) => device.open(self.descriptor)?,
+            #[cfg(feature = "mock")]
+            DeviceSource::Simulated(device) => device.open(),
+        };
+        device_handle.timeout = context.timeout;
+        device_handle.retry_policy = context.retry_policy;
+        Ok(device_handle)
+    }
+
+    /// Returns an [`OwnedDevice`] which doesn't borrow from the context, so it can be stored or
+    /// sent to another thread.
+    #[must_use]
+    pub fn to_owned(&self) -> OwnedDevice {
+        OwnedDevice {
+            path: self.path().to_owned(),
+            serial_number: self.serial_number().map(String::from),
+            product_id: self.descriptor.product_id,
+            descriptor: self.descriptor,
+        }
+    }
+}
+
+/// A device that can be used, which unlike [`Device`] doesn't borrow from the context. It is
+/// created with [`Device::to_owned`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct OwnedDevice {
+    path: CString,
+    serial_number: Option<String>,
+    product_id: u16,
+    descriptor: DeviceDescriptor,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl OwnedDevice {
+    /// The path of the device when it was listed, as returned by [`Device::path`].
+    #[must_use]
+    pub fn path(&self) -> &CStr {
+        &self.path
+    }
+
+    /// The serial number of the device, if it has one.
+    #[must_use]
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    /// The model of the device.
+    #[must_use]
+    pub fn device_type(&self) -> DeviceType {
+        self.descriptor.device_type
+    }
+
+    /// The descriptor used to communicate with the device.
+    #[must_use]
+    pub fn descriptor(&self) -> &DeviceDescriptor {
+        &self.descriptor
+    }
+
+    /// Finds the device in the context's list of connected devices and opens it, like
+    /// [`Device::open`]. The device is found by its path, or by its serial number if it has been
+    /// reconnected since, so the context's list may need to be refreshed first with
+    /// [`Litra::refresh_connected_devices`].
+    ///
+    /// Fails with [`DeviceError::NotConnected`] if the device can't be found.
+    pub fn open(&self, context: &Litra) -> DeviceResult<DeviceHandle> {
+        let device_list = || {
+            context
+                .get_connected_devices()
+                .filter(|device| device.descriptor.product_id == self.product_id)
+        };
+        let device = device_list()
+            .find(|device| device.path() == self.path.as_c_str())
+            .or_else(|| {
+                let serial_number = self.serial_number.as_deref()?;
+                device_list().find(|device| device.serial_number() == Some(serial_number))
+            })
+            .ok_or(DeviceError::NotConnected)?;
+
+        Device {
+            source: device.source,
+            descriptor: self.descriptor,
+        }
+        .open(context)
+    }
+}
+
+/// A change to the connected devices, reported by a [`DeviceWatcher`].
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub enum DeviceEvent {
+    /// A device was connected, or was already connected when the watcher started.
+    Connected(OwnedDevice),
+    /// A device was disconnected.
+    Disconnected(OwnedDevice),
+}
+
+/// Watches for devices being connected and disconnected on a background thread, sending a
+/// [`DeviceEvent`] for each change.
+///
+/// hidapi can't be notified of changes on every platform, so the watcher checks the connected
+/// devices at an interval. A device which is reconnected quicker than the interval may not be
+/// reported. The context is shared with the watcher so that devices can still be opened while it
+/// runs. The watcher stops when it is dropped.
+///
+/// ```no_run
+/// use litra::{DeviceEvent, DeviceWatcher, Litra};
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+///
+/// let context = Arc::new(Mutex::new(Litra::new().expect("Failed to initialize litra.")));
+/// let watcher = DeviceWatcher::new(Arc::clone(&context), Duration::from_millis(250));
+/// while let Some(event) = watcher.recv() {
+///     if let DeviceEvent::Connected(device) = event {
+///         let context = context.lock().unwrap();
+///         if let Ok(handle) = device.open(&context) {
+///             let _ = handle.set_on(true);
+///         }
+///     }
+/// }
+/// ```
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct DeviceWatcher {
+    events: mpsc::Receiver<DeviceEvent>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DeviceWatcher {
+    /// Starts watching for devices using the context, checking every `poll_interval`. A
+    /// [`DeviceEvent::Connected`] event is sent straight away for each device which is already
+    /// connected.
+    #[must_use]
+    pub fn new(context: Arc<Mutex<Litra>>, poll_interval: Duration) -> Self {
+        let (sender, events) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread = thread::spawn({
+            let stopped = Arc::clone(&stopped);
+            move || {
+                let mut known_devices: Vec<OwnedDevice> = Vec::new();
+                while !stopped.load(Ordering::Relaxed) {
+                    let connected_devices = {
+                        let mut context = context.lock().unwrap_or_else(PoisonError::into_inner);
+                        // Failures are usually temporary, so they're retried on the next check
+                        context.refresh_connected_devices().ok().map(|()| {
+                            context
+                                .get_connected_devices()
+                                .map(|device| device.to_owned())
+                                .collect::<Vec<_>>()
+                        })
+                    };
+
+                    if let Some(connected_devices) = connected_devices {
+                        let is_in = |devices: &[OwnedDevice], device: &OwnedDevice| {
+                            devices.iter().any(|other| other.path == device.path)
+                        };
+                        let events = connected_devices
+                            .iter()
+                            .filter(|device| !is_in(&known_devices, device))
+                            .map(|device| DeviceEvent::Connected(device.clone()))
+                            .chain(
+                                known_devices
+                                    .iter()
+                                    .filter(|device| !is_in(&connected_devices, device))
+                                    .map(|device| DeviceEvent::Disconnected(device.clone())),
+                            );
+                        for event in events {
+                            if sender.send(event).is_err() {
+                                return;
+                            }
+                        }
+                        known_devices = connected_devices;
+                    }
+
+                    thread::park_timeout(poll_interval);
+                }
+            }
+        });
+
+        DeviceWatcher {
+            events,
+            stopped,
+            thread: Some(thread),
+        }
+    }
+
+    /// Waits for the next event. Returns `None` if the watcher has stopped.
+    #[must_use]
+    pub fn recv(&self) -> Option<DeviceEvent> {
+        self.events.recv().ok()
+    }
+
+    /// Waits up to `timeout` for the next event. Returns `None` if there wasn't one.
+    #[must_use]
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<DeviceEvent> {
+        self.events.recv_timeout(timeout).ok()
+    }
+
+    /// Returns the next event if there is one, without waiting.
+    #[must_use]
+    pub fn try_recv(&self) -> Option<DeviceEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for DeviceWatcher {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
     }
 }
 
 /// The handle of an opened device that can be used for getting and setting the device status.
 #[derive(Debug)]
 pub struct DeviceHandle {
-    hid_device: HidDevice,
-    device_type: DeviceType,
+    transport: Box<dyn Transport>,
+    descriptor: DeviceDescriptor,
+    report_type: Cell<ReportType>,
+    fallback_report_type: Option<ReportType>,
+    firmware_version: OnceCell<FirmwareVersion>,
+    timeout: Duration,
+    retry_policy: RetryPolicy,
 }
 
 impl DeviceHandle {
+    /// Creates a handle for a device which is reached through a custom [`Transport`], rather than
+    /// opened with [`Device::open`]. The descriptor describes how to communicate with the device,
+    /// and can be found with [`DeviceDescriptor::for_device_type`] for supported models.
+    ///
+    /// The handle uses the default timeout and [`RetryPolicy`], which can be changed with
+    /// [`DeviceHandle::set_timeout`] and [`DeviceHandle::set_retry_policy`].
+    pub fn from_transport(
+        transport: impl Transport + 'static,
+        descriptor: DeviceDescriptor,
+    ) -> DeviceHandle {
+        let (report_type, fallback_report_type) = detect_report_types(&transport);
+        DeviceHandle {
+            transport: Box::new(transport),
+            descriptor,
+            report_type: Cell::new(report_type),
+            fallback_report_type,
+            firmware_version: OnceCell::new(),
+            timeout: DEFAULT_TIMEOUT,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
     /// The model of the device.
     #[must_use]
     pub fn device_type(&self) -> DeviceType {
-        self.device_type
+        self.descriptor.device_type
     }
 
-    /// The [`HidDevice`] for the device.
+    /// The descriptor used to communicate with the device.
     #[must_use]
-    pub fn hid_device(&self) -> &HidDevice {
-        &self.hid_device
+    pub fn descriptor(&self) -> &DeviceDescriptor {
+        &self.descriptor
+    }
+
+    /// Sets how long to wait for the device to respond before failing with
+    /// [`DeviceError::Timeout`]. Defaults to the timeout of the [`Litra`] context the device was
+    /// opened from.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Sets how to retry operations which fail with an error which may be temporary. Defaults to
+    /// the retry policy of the [`Litra`] context the device was opened from.
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// The [`HidDevice`] for the device, or `None` if it isn't a real device, e.g. a simulated
+    /// device.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[must_use]
+    pub fn hid_device(&self) -> Option<&HidDevice> {
+        self.transport.hid_device()
     }
 
     /// Returns the serial number of the device.
     pub fn serial_number(&self) -> DeviceResult<Option<String>> {
-        match self.hid_device.get_device_info() {
-            Ok(device_info) => Ok(device_info.serial_number().map(String::from)),
-            Err(error) => Err(DeviceError::HidError(error)),
-        }
+        self.transport.serial_number()
     }
 
     /// Queries the current power status of the device. Returns `true` if the device is currently on.
     pub fn is_on(&self) -> DeviceResult<bool> {
-        let message = generate_is_on_bytes(&self.device_type);
+        let response = self.request(self.descriptor.feature_index, GET_ON_FUNCTION, &[])?;
 
-        self.hid_device.write(&message)?;
-
-        let mut response_buffer = [0x00; 20];
-        let response = self.hid_device.read(&mut response_buffer[..])?;
-
-        Ok(response_buffer[..response][4] == 1)
+        Ok(response[4] == 1)
     }
 
     /// Sets the power status of the device. Turns the device on if `true` is passed and turns it
     /// of on `false`.
     pub fn set_on(&self, on: bool) -> DeviceResult<()> {
-        let message = generate_set_on_bytes(&self.device_type, on);
+        let message = generate_set_on_bytes(self.descriptor.feature_index, on);
 
-        self.hid_device.write(&message)?;
+        self.write_message(&message)?;
         Ok(())
     }
 
     /// Queries the device's current brightness in Lumen.
     pub fn brightness_in_lumen(&self) -> DeviceResult<u16> {
-        let message = generate_get_brightness_in_lumen_bytes(&self.device_type);
-
-        self.hid_device.write(&message)?;
+        let response = self.request(
+            self.descriptor.feature_index,
+            GET_BRIGHTNESS_IN_LUMEN_FUNCTION,
+            &[],
+        )?;
 
-        let mut response_buffer = [0x00; 20];
-        let response = self.hid_device.read(&mut response_buffer[..])?;
-
-        Ok(u16::from(response_buffer[..response][4]) * 256
-            + u16::from(response_buffer[..response][5]))
+        Ok(u16::from_be_bytes([response[4], response[5]]))
     }
 
     /// Sets the device's brightness in Lumen.
     pub fn set_brightness_in_lumen(&self, brightness_in_lumen: u16) -> DeviceResult<()> {
-        if brightness_in_lumen < self.minimum_brightness_in_lumen()
-            || brightness_in_lumen > self.maximum_brightness_in_lumen()
-        {
+        if !self.is_valid_brightness_in_lumen(brightness_in_lumen) {
             return Err(DeviceError::InvalidBrightness(brightness_in_lumen));
         }
 
-        let message =
-            generate_set_brightness_in_lumen_bytes(&self.device_type, brightness_in_lumen);
+        let message = generate_set_brightness_in_lumen_bytes(
+            self.descriptor.feature_index,
+            brightness_in_lumen,
+        );
 
-        self.hid_device.write(&message)?;
+        self.write_message(&message)?;
         Ok(())
     }
 
     /// Returns the minimum brightness supported by the device in Lumen.
     #[must_use]
     pub fn minimum_brightness_in_lumen(&self) -> u16 {
-        match self.device_type {
-            DeviceType::LitraGlow => 20,
-            DeviceType::LitraBeam | DeviceType::LitraBeamLX => 30,
-        }
+        self.descriptor.minimum_brightness_in_lumen
     }
 
     /// Returns the maximum brightness supported by the device in Lumen.
     #[must_use]
     pub fn maximum_brightness_in_lumen(&self) -> u16 {
-        match self.device_type {
-            DeviceType::LitraGlow => 250,
-            DeviceType::LitraBeam | DeviceType::LitraBeamLX => 400,
-        }
+        self.descriptor.maximum_brightness_in_lumen
+    }
+
+    /// Returns the difference between consecutive brightness values supported by the device in
+    /// Lumen.
+    #[must_use]
+    pub fn brightness_step_in_lumen(&self) -> u16 {
+        self.descriptor.brightness_step_in_lumen()
+    }
+
+    /// Returns whether the device supports the given brightness in Lumen.
+    #[must_use]
+    pub fn is_valid_brightness_in_lumen(&self, brightness_in_lumen: u16) -> bool {
+        self.descriptor
+            .is_valid_brightness_in_lumen(brightness_in_lumen)
+    }
+
+    /// Returns an [`Iterator`] of every brightness supported by the device in Lumen, from the
+    /// minimum to the maximum.
+    pub fn valid_brightnesses_in_lumen(&self) -> impl Iterator<Item = u16> {
+        (self.minimum_brightness_in_lumen()..=self.maximum_brightness_in_lumen())
+            .step_by(usize::from(self.brightness_step_in_lumen()))
+    }
+
+    /// Discovers which brightness values the device can actually represent, by setting each
+    /// supported brightness in turn and reading back the value the device reports. Some devices
+    /// round brightness values to internal steps, so this can be used to snap values to ones
+    /// which will be read back unchanged.
+    ///
+    /// This sends two requests for every supported brightness, so it takes a few seconds, and the
+    /// light visibly changes while it's on. The original brightness is restored afterwards.
+    pub fn discover_brightness_steps(&self) -> DeviceResult<BrightnessSteps> {
+        let original_brightness_in_lumen = self.brightness_in_lumen()?;
+        let steps_in_lumen = self
+            .valid_brightnesses_in_lumen()
+            .map(|brightness_in_lumen| {
+                self.set_brightness_in_lumen(brightness_in_lumen)?;
+                self.brightness_in_lumen()
+            })
+            .collect::<DeviceResult<BTreeSet<u16>>>();
+        self.set_brightness_in_lumen(original_brightness_in_lumen)?;
+
+        Ok(BrightnessSteps {
+            steps_in_lumen: steps_in_lumen?.into_iter().collect(),
+        })
     }
 
     /// Queries the device's current color temperature in Kelvin.
     pub fn temperature_in_kelvin(&self) -> DeviceResult<u16> {
-        let message = generate_get_temperature_in_kelvin_bytes(&self.device_type);
-
-        self.hid_device.write(&message)?;
+        let response = self.request(
+            self.descriptor.feature_index,
+            GET_TEMPERATURE_IN_KELVIN_FUNCTION,
+            &[],
+        )?;
 
-        let mut response_buffer = [0x00; 20];
-        let response = self.hid_device.read(&mut response_buffer[..])?;
-        Ok(u16::from(response_buffer[..response][4]) * 256
-            + u16::from(response_buffer[..response][5]))
+        Ok(u16::from_be_bytes([response[4], response[5]]))
     }
 
     /// Sets the device's color temperature in Kelvin.
     pub fn set_temperature_in_kelvin(&self, temperature_in_kelvin: u16) -> DeviceResult<()> {
-        if temperature_in_kelvin < self.minimum_temperature_in_kelvin()
-            || temperature_in_kelvin > self.maximum_temperature_in_kelvin()
-            || (temperature_in_kelvin % 100) != 0
-        {
+        if !self.is_valid_temperature_in_kelvin(temperature_in_kelvin) {
             return Err(DeviceError::InvalidTemperature(temperature_in_kelvin));
         }
 
-        let message =
-            generate_set_temperature_in_kelvin_bytes(&self.device_type, temperature_in_kelvin);
+        let message = generate_set_temperature_in_kelvin_bytes(
+            self.descriptor.feature_index,
+            temperature_in_kelvin,
+        );
 
-        self.hid_device.write(&message)?;
+        self.write_message(&message)?;
         Ok(())
     }
 
     /// Returns the minimum color temperature supported by the device in Kelvin.
     #[must_use]
     pub fn minimum_temperature_in_kelvin(&self) -> u16 {
-        MINIMUM_TEMPERATURE_IN_KELVIN
+        self.descriptor.minimum_temperature_in_kelvin
     }
 
     /// Returns the maximum color temperature supported by the device in Kelvin.
     #[must_use]
     pub fn maximum_temperature_in_kelvin(&self) -> u16 {
-        MAXIMUM_TEMPERATURE_IN_KELVIN
+        self.descriptor.maximum_temperature_in_kelvin
+    }
+
+    /// Returns the difference between consecutive color temperatures supported by the device in
+    /// Kelvin.
+    #[must_use]
+    pub fn temperature_step_in_kelvin(&self) -> u16 {
+        self.descriptor.temperature_step_in_kelvin()
+    }
+
+    /// Returns whether the device supports the given color temperature in Kelvin.
+    #[must_use]
+    pub fn is_valid_temperature_in_kelvin(&self, temperature_in_kelvin: u16) -> bool {
+        self.descriptor
+            .is_valid_temperature_in_kelvin(temperature_in_kelvin)
+    }
+
+    /// Returns an [`Iterator`] of every color temperature supported by the device in Kelvin, from
+    /// the minimum to the maximum.
+    pub fn valid_temperatures_in_kelvin(&self) -> impl Iterator<Item = u16> {
+        (self.minimum_temperature_in_kelvin()..=self.maximum_temperature_in_kelvin())
+            .step_by(usize::from(self.temperature_step_in_kelvin()))
+    }
+
+    /// Returns what the device supports.
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        self.descriptor.capabilities()
+    }
+
+    /// Queries the device's firmware version using the HID++ device information feature. The
+    /// result is cached for the lifetime of the handle.
+    pub fn firmware_version(&self) -> DeviceResult<FirmwareVersion> {
+        if let Some(firmware_version) = self.firmware_version.get() {
+            return Ok(firmware_version.clone());
+        }
+
+        let feature_index = self.feature_index(DEVICE_INFORMATION_FEATURE_ID)?;
+        // Entity 0 is the main application firmware
+        let response = self.request(feature_index, GET_FIRMWARE_INFO_FUNCTION, &[0x00])?;
+
+        let firmware_version = FirmwareVersion::from_response(&response);
+        Ok(self
+            .firmware_version
+            .get_or_init(|| firmware_version)
+            .clone())
+    }
+
+    /// Looks up the index of a HID++ feature on the device using the root feature. Returns
+    /// [`DeviceError::Unsupported`] if the device doesn't have the feature.
+    fn feature_index(&self, feature_id: u16) -> DeviceResult<u8> {
+        let response = self.request(
+            ROOT_FEATURE_INDEX,
+            GET_FEATURE_FUNCTION,
+            &feature_id.to_be_bytes(),
+        )?;
+        match response[4] {
+            0x00 => Err(DeviceError::Unsupported),
+            feature_index => Ok(feature_index),
+        }
+    }
+
+    /// Sends a raw HID++ 2.0 request to the device and returns its response, for experimenting
+    /// with commands that this crate doesn't support yet.
+    ///
+    /// `command` is the 4-bit function number within the feature, and `payload` can be at most
+    /// 16 bytes long, or 3 bytes long for devices which use short reports. If the device reports an
+    /// error, it is returned as a [`RawResponse`] for which [`RawResponse::error_code`] returns
+    /// `Some`.
+    ///
+    /// **This is unstable.** Sending undocumented commands may leave the device in an unexpected
+    /// state, and this method may change or be removed in any release.
+    pub fn send_raw(
+        &self,
+        feature_index: u8,
+        command: u8,
+        payload: &[u8],
+    ) -> DeviceResult<RawResponse> {
+        if command > 0x0f {
+            return Err(DeviceError::InvalidRawRequest(
+                "command must be between 0 and 15",
+            ));
+        }
+        if payload.len() > ReportType::Long.maximum_parameters_length() {
+            return Err(DeviceError::InvalidRawRequest(
+                "payload must be at most 16 bytes long",
+            ));
+        }
+        if self.report_type.get() == ReportType::Short
+            && payload.len() > ReportType::Short.maximum_parameters_length()
+        {
+            return Err(DeviceError::InvalidRawRequest(
+                "payload must be at most 3 bytes long for devices which use short reports",
+            ));
+        }
+
+        self.exchange(feature_index, command, payload)
+    }
+
+    /// Sends a HID++ request and returns the response, mapping error responses to
+    /// [`DeviceError::Unsupported`].
+    fn request(
+        &self,
+        feature_index: u8,
+        function: u8,
+        parameters: &[u8],
+    ) -> DeviceResult<[u8; 20]> {
+        let response = self.exchange(feature_index, function, parameters)?;
+        if response.error_code().is_some() {
+            return Err(DeviceError::Unsupported);
+        }
+        Ok(response.report)
+    }
+
+    /// Sends a HID++ request and waits for the matching response or error, skipping any unrelated
+    /// reports (e.g. notifications) received in the meantime.
+    fn exchange(
+        &self,
+        feature_index: u8,
+        function: u8,
+        parameters: &[u8],
+    ) -> DeviceResult<RawResponse> {
+        let message = generate_request_bytes(feature_index, function, parameters);
+
+        self.with_retries(|| self.exchange_once(&message))
+    }
+
+    fn exchange_once(&self, message: &[u8; 20]) -> DeviceResult<RawResponse> {
+        self.write_message_once(message)?;
+
+        for _ in 0..MAXIMUM_UNRELATED_RESPONSES {
+            let mut report = [0x00; 20];
+            if self.transport.read_timeout(&mut report, self.timeout)? == 0 {
+                return Err(DeviceError::Timeout);
+            }
+
+            if is_response_to(message, &report) {
+                return Ok(RawResponse { report });
+            }
+        }
+
+        Err(DeviceError::Unsupported)
+    }
+
+    /// Writes a HID++ message, retrying according to the handle's [`RetryPolicy`].
+    fn write_message(&self, message: &[u8; 20]) -> DeviceResult<()> {
+        self.with_retries(|| self.write_message_once(message))
+    }
+
+    /// Runs an operation, retrying it with exponential backoff according to the handle's
+    /// [`RetryPolicy`] if it fails with an error which may be temporary.
+    fn with_retries<T>(&self, operation: impl Fn() -> DeviceResult<T>) -> DeviceResult<T> {
+        let mut retry = 0;
+        loop {
+            match operation() {
+                Err(error) if error.is_temporary() && retry < self.retry_policy.retries => {
+                    thread::sleep(self.retry_policy.backoff(retry));
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Writes a HID++ message using the current report format. If the write fails and the device
+    /// may support the other report format, the message is retried in that format, which is then
+    /// used for all subsequent messages.
+    fn write_message_once(&self, message: &[u8; 20]) -> DeviceResult<()> {
+        let report_type = self.report_type.get();
+
+        match self.transport.write(&report_type.encode(message)) {
+            Ok(()) => Ok(()),
+            Err(error) => match self.fallback_report_type {
+                Some(fallback_report_type)
+                    if fallback_report_type.can_encode(message)
+                        && self
+                            .transport
+                            .write(&fallback_report_type.encode(message))
+                            .is_ok() =>
+                {
+                    self.report_type.set(fallback_report_type);
+                    Ok(())
+                }
+                _ => Err(error),
+            },
+        }
     }
 }
 
-const VENDOR_ID: u16 = 0x046d;
-const USAGE_PAGE: u16 = 0xff43;
+/// How a [`DeviceHandle`] exchanges HID reports with its device.
+///
+/// This is implemented for [`HidDevice`], which is used for devices opened with [`Device::open`].
+/// Other backends, like a connection to another machine, can implement it and be used
+/// with [`DeviceHandle::from_transport`]. Errors which aren't a [`DeviceError`] can be returned as
+/// [`DeviceError::TransportError`].
+pub trait Transport: fmt::Debug + Send {
+    /// Writes a report, including its report ID.
+    fn write(&self, report: &[u8]) -> DeviceResult<()>;
+
+    /// Reads a report into the buffer, waiting up to `timeout` for one to arrive. Returns the
+    /// number of bytes read, which is 0 if the timeout expired.
+    fn read_timeout(&self, report: &mut [u8], timeout: Duration) -> DeviceResult<usize>;
 
-fn device_type_from_product_id(product_id: u16) -> Option<DeviceType> {
-    match product_id {
-        0xc900 => DeviceType::LitraGlow.into(),
-        0xc901 => DeviceType::LitraBeam.into(),
-        0xb901 => DeviceType::LitraBeam.into(),
-        0xc903 => DeviceType::LitraBeamLX.into(),
-        _ => None,
+    /// Returns the serial number of the device.
+    fn serial_number(&self) -> DeviceResult<Option<String>>;
+
+    /// Reads the device's HID report descriptor into the buffer, returning its length. This is
+    /// used to work out which HID++ report format the device uses. By default, this fails with
+    /// [`DeviceError::Unsupported`], and both formats are tried.
+    fn report_descriptor(&self, _descriptor: &mut [u8]) -> DeviceResult<usize> {
+        Err(DeviceError::Unsupported)
+    }
+
+    /// Returns the underlying [`HidDevice`], if there is one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn hid_device(&self) -> Option<&HidDevice> {
+        None
     }
 }
 
-const MINIMUM_TEMPERATURE_IN_KELVIN: u16 = 2700;
-const MAXIMUM_TEMPERATURE_IN_KELVIN: u16 = 6500;
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for HidDevice {
+    fn write(&self, report: &[u8]) -> DeviceResult<()> {
+        HidDevice::write(self, report)?;
+        Ok(())
+    }
 
-fn generate_is_on_bytes(device_type: &DeviceType) -> [u8; 20] {
-    match device_type {
-        DeviceType::LitraGlow | DeviceType::LitraBeam => [
-            0x11, 0xff, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
-        DeviceType::LitraBeamLX => [
-            0x11, 0xff, 0x06, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
+    fn read_timeout(&self, report: &mut [u8], timeout: Duration) -> DeviceResult<usize> {
+        let timeout_in_milliseconds = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+        Ok(HidDevice::read_timeout(
+            self,
+            report,
+            timeout_in_milliseconds,
+        )?)
+    }
+
+    fn serial_number(&self) -> DeviceResult<Option<String>> {
+        Ok(self.get_device_info()?.serial_number().map(String::from))
+    }
+
+    fn report_descriptor(&self, descriptor: &mut [u8]) -> DeviceResult<usize> {
+        Ok(self.get_report_descriptor(descriptor)?)
+    }
+
+    fn hid_device(&self) -> Option<&HidDevice> {
+        Some(self)
     }
 }
 
-fn generate_get_brightness_in_lumen_bytes(device_type: &DeviceType) -> [u8; 20] {
-    match device_type {
-        DeviceType::LitraGlow | DeviceType::LitraBeam => [
-            0x11, 0xff, 0x04, 0x31, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
-        DeviceType::LitraBeamLX => [
-            0x11, 0xff, 0x06, 0x31, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
+/// How to retry operations which fail with an error which may be temporary, i.e.
+/// [`DeviceError::HidError`], [`DeviceError::TransportError`] or [`DeviceError::Timeout`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// The number of times to retry a failed operation.
+    pub retries: u32,
+    /// How long to wait before the first retry. This doubles after each retry, up to
+    /// `max_backoff`.
+    pub initial_backoff: Duration,
+    /// The longest to wait before a retry, so that many retries don't wait for minutes.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 0,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        }
     }
 }
 
-fn generate_get_temperature_in_kelvin_bytes(device_type: &DeviceType) -> [u8; 20] {
-    match device_type {
-        DeviceType::LitraGlow | DeviceType::LitraBeam => [
-            0x11, 0xff, 0x04, 0x81, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
-        DeviceType::LitraBeamLX => [
-            0x11, 0xff, 0x06, 0x81, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
+impl RetryPolicy {
+    /// Returns how long to wait before a retry, where the first retry is 0.
+    #[must_use]
+    pub fn backoff(&self, retry: u32) -> Duration {
+        1u32.checked_shl(retry)
+            .and_then(|multiplier| self.initial_backoff.checked_mul(multiplier))
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
     }
 }
 
-fn generate_set_on_bytes(device_type: &DeviceType, on: bool) -> [u8; 20] {
-    let on_byte = if on { 0x01 } else { 0x00 };
-    match device_type {
-        DeviceType::LitraGlow | DeviceType::LitraBeam => [
-            0x11, 0xff, 0x04, 0x1c, on_byte, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
-        DeviceType::LitraBeamLX => [
-            0x11, 0xff, 0x06, 0x1c, on_byte, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-        ],
+/// Describes how to communicate with a device: its product ID, the HID++ feature index used for
+/// lighting commands and the ranges of brightness and color temperature it supports.
+///
+/// Descriptors for supported devices are built in. Additional devices can be registered with
+/// [`Litra::register_device_descriptor`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceDescriptor {
+    /// The USB product ID of the device.
+    pub product_id: u16,
+    /// The model which the device is treated as.
+    pub device_type: DeviceType,
+    /// The index of the HID++ illumination feature on the device.
+    pub feature_index: u8,
+    /// The minimum brightness supported by the device in Lumen.
+    pub minimum_brightness_in_lumen: u16,
+    /// The maximum brightness supported by the device in Lumen.
+    pub maximum_brightness_in_lumen: u16,
+    /// The minimum color temperature supported by the device in Kelvin.
+    pub minimum_temperature_in_kelvin: u16,
+    /// The maximum color temperature supported by the device in Kelvin.
+    pub maximum_temperature_in_kelvin: u16,
+}
+
+impl DeviceDescriptor {
+    /// Returns the built-in descriptor for a model, using the first product ID it is known by.
+    #[must_use]
+    pub fn for_device_type(device_type: DeviceType) -> Self {
+        let product_id = PRODUCT_IDS
+            .iter()
+            .find(|(_, supported_device_type)| *supported_device_type == device_type)
+            .map_or(0, |(product_id, _)| *product_id);
+        DeviceDescriptor::built_in(product_id, device_type)
+    }
+
+    /// Returns the built-in descriptor for a product ID, or `None` if the device isn't supported.
+    #[must_use]
+    pub fn for_product_id(product_id: u16) -> Option<Self> {
+        PRODUCT_IDS
+            .iter()
+            .find(|(supported_product_id, _)| *supported_product_id == product_id)
+            .map(|(_, device_type)| DeviceDescriptor::built_in(product_id, *device_type))
+    }
+
+    fn built_in(product_id: u16, device_type: DeviceType) -> Self {
+        DeviceDescriptor {
+            product_id,
+            device_type,
+            feature_index: device_type.feature_index(),
+            minimum_brightness_in_lumen: device_type.minimum_brightness_in_lumen(),
+            maximum_brightness_in_lumen: device_type.maximum_brightness_in_lumen(),
+            minimum_temperature_in_kelvin: device_type.minimum_temperature_in_kelvin(),
+            maximum_temperature_in_kelvin: device_type.maximum_temperature_in_kelvin(),
+        }
+    }
+
+    /// Checks that the descriptor's ranges aren't inverted, since code using them relies on the
+    /// minimum being no greater than the maximum.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn validate(&self) -> DeviceResult<()> {
+        if self.minimum_brightness_in_lumen > self.maximum_brightness_in_lumen {
+            return Err(DeviceError::InvalidDeviceDescriptor(
+                "the minimum brightness is greater than the maximum brightness",
+            ));
+        }
+        if self.minimum_temperature_in_kelvin > self.maximum_temperature_in_kelvin {
+            return Err(DeviceError::InvalidDeviceDescriptor(
+                "the minimum temperature is greater than the maximum temperature",
+            ));
+        }
+        Ok(())
+    }
+
+    fn brightness_step_in_lumen(&self) -> u16 {
+        BRIGHTNESS_STEP_IN_LUMEN
+    }
+
+    fn temperature_step_in_kelvin(&self) -> u16 {
+        TEMPERATURE_STEP_IN_KELVIN
+    }
+
+    fn is_valid_brightness_in_lumen(&self, brightness_in_lumen: u16) -> bool {
+        (self.minimum_brightness_in_lumen..=self.maximum_brightness_in_lumen)
+            .contains(&brightness_in_lumen)
+            && (brightness_in_lumen - self.minimum_brightness_in_lumen)
+                % self.brightness_step_in_lumen()
+                == 0
+    }
+
+    fn is_valid_temperature_in_kelvin(&self, temperature_in_kelvin: u16) -> bool {
+        (self.minimum_temperature_in_kelvin..=self.maximum_temperature_in_kelvin)
+            .contains(&temperature_in_kelvin)
+            && temperature_in_kelvin % self.temperature_step_in_kelvin() == 0
+    }
+
+    /// Returns what a device with this descriptor supports.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            device_type: self.device_type,
+            minimum_brightness_in_lumen: self.minimum_brightness_in_lumen,
+            maximum_brightness_in_lumen: self.maximum_brightness_in_lumen,
+            brightness_step_in_lumen: self.brightness_step_in_lumen(),
+            minimum_temperature_in_kelvin: self.minimum_temperature_in_kelvin,
+            maximum_temperature_in_kelvin: self.maximum_temperature_in_kelvin,
+            temperature_step_in_kelvin: self.temperature_step_in_kelvin(),
+            supports_rgb: self.device_type == DeviceType::LitraBeamLX,
+            zones: match self.device_type {
+                DeviceType::LitraBeamLX => LITRA_BEAM_LX_RGB_ZONES,
+                DeviceType::LitraGlow | DeviceType::LitraBeam => 0,
+            },
+            supports_power_on_defaults: false,
+        }
     }
 }
 
-fn generate_set_brightness_in_lumen_bytes(
-    device_type: &DeviceType,
-    brightness_in_lumen: u16,
-) -> [u8; 20] {
-    let brightness_bytes = brightness_in_lumen.to_be_bytes();
-
-    match device_type {
-        DeviceType::LitraGlow | DeviceType::LitraBeam => [
-            0x11,
-            0xff,
-            0x04,
-            0x4c,
-            brightness_bytes[0],
-            brightness_bytes[1],
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-        ],
-        DeviceType::LitraBeamLX => [
-            0x11,
-            0xff,
-            0x06,
-            0x4c,
-            brightness_bytes[0],
-            brightness_bytes[1],
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-        ],
+/// What a device supports, so that user interfaces can adapt to it. This is returned by
+/// [`DeviceHandle::capabilities`].
+///
+/// More fields may be added as support for more features is added, so this can't be constructed
+/// outside of this crate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// The model of the device.
+    pub device_type: DeviceType,
+    /// The minimum brightness supported by the device in Lumen.
+    pub minimum_brightness_in_lumen: u16,
+    /// The maximum brightness supported by the device in Lumen.
+    pub maximum_brightness_in_lumen: u16,
+    /// The difference between consecutive brightness values supported by the device in Lumen.
+    pub brightness_step_in_lumen: u16,
+    /// The minimum color temperature supported by the device in Kelvin.
+    pub minimum_temperature_in_kelvin: u16,
+    /// The maximum color temperature supported by the device in Kelvin.
+    pub maximum_temperature_in_kelvin: u16,
+    /// The difference between consecutive color temperatures supported by the device in Kelvin.
+    pub temperature_step_in_kelvin: u16,
+    /// Whether the device has RGB lighting, like the back of the Litra Beam LX. This library
+    /// can't control RGB lighting yet.
+    pub supports_rgb: bool,
+    /// The number of separately controllable RGB lighting zones, or 0 if the device doesn't have
+    /// RGB lighting.
+    pub zones: u8,
+    /// Whether the state the device turns on in can be configured. None of the supported devices
+    /// allow this yet.
+    pub supports_power_on_defaults: bool,
+}
+
+/// The brightness values a device can actually represent, as discovered by
+/// [`DeviceHandle::discover_brightness_steps`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrightnessSteps {
+    steps_in_lumen: Vec<u16>,
+}
+
+impl BrightnessSteps {
+    /// The brightness values the device reported, in Lumen, from lowest to highest.
+    #[must_use]
+    pub fn steps_in_lumen(&self) -> &[u16] {
+        &self.steps_in_lumen
+    }
+
+    /// Returns the representable brightness closest to the given brightness in Lumen.
+    #[must_use]
+    pub fn snap(&self, brightness_in_lumen: u16) -> u16 {
+        self.steps_in_lumen
+            .iter()
+            .copied()
+            .min_by_key(|step_in_lumen| step_in_lumen.abs_diff(brightness_in_lumen))
+            .unwrap_or(brightness_in_lumen)
+    }
+}
+
+/// The firmware version of a device, as reported by the HID++ device information feature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FirmwareVersion {
+    /// The name of the firmware, e.g. `RQM`.
+    pub prefix: String,
+    /// The firmware's version number.
+    pub number: u8,
+    /// The firmware's revision number.
+    pub revision: u8,
+    /// The firmware's build number.
+    pub build: u16,
+}
+
+impl FirmwareVersion {
+    /// Parses a response to the device information feature's `getFwInfo` function.
+    fn from_response(response: &[u8; 20]) -> Self {
+        FirmwareVersion {
+            prefix: String::from_utf8_lossy(&response[5..8]).trim().to_string(),
+            number: response[8],
+            revision: response[9],
+            build: u16::from_be_bytes([response[10], response[11]]),
+        }
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:02X}.{:02X}.B{:04X}",
+            self.prefix, self.number, self.revision, self.build
+        )
+    }
+}
+
+/// A response to a request sent with [`DeviceHandle::send_raw`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawResponse {
+    report: [u8; 20],
+}
+
+impl RawResponse {
+    /// Returns the full HID++ report, including the report ID, device index, feature index and
+    /// function.
+    #[must_use]
+    pub fn report(&self) -> &[u8; 20] {
+        &self.report
+    }
+
+    /// Returns the response's parameters, following the 4-byte header.
+    #[must_use]
+    pub fn payload(&self) -> &[u8] {
+        &self.report[4..]
+    }
+
+    /// Returns the HID++ error code if the device rejected the request.
+    #[must_use]
+    pub fn error_code(&self) -> Option<u8> {
+        if self.report[2] == ERROR_FEATURE_INDEX {
+            Some(self.report[5])
+        } else {
+            None
+        }
+    }
+}
+
+/// The HID++ report format used to communicate with a device.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReportType {
+    /// A 7 byte report with report ID `0x10`.
+    Short,
+    /// A 20 byte report with report ID `0x11`.
+    Long,
+}
+
+impl ReportType {
+    fn report_id(self) -> u8 {
+        match self {
+            ReportType::Short => SHORT_REPORT_ID,
+            ReportType::Long => LONG_REPORT_ID,
+        }
+    }
+
+    fn length(self) -> usize {
+        match self {
+            ReportType::Short => 7,
+            ReportType::Long => 20,
+        }
+    }
+
+    fn other(self) -> ReportType {
+        match self {
+            ReportType::Short => ReportType::Long,
+            ReportType::Long => ReportType::Short,
+        }
+    }
+
+    /// The number of bytes of parameters which fit in a report, after the report ID, device index,
+    /// feature index and function.
+    fn maximum_parameters_length(self) -> usize {
+        self.length() - 4
+    }
+
+    /// Returns whether a long HID++ message can be re-encoded in this report format without losing
+    /// any of its parameters.
+    fn can_encode(self, message: &[u8; 20]) -> bool {
+        message[self.length()..].iter().all(|byte| *byte == 0x00)
+    }
+
+    /// Re-encodes a long HID++ message in this report format. Parameters which don't fit are
+    /// dropped, so check [`ReportType::can_encode`] first for messages with more than three bytes
+    /// of parameters, like those sent with [`DeviceHandle::send_raw`].
+    fn encode(self, message: &[u8; 20]) -> Vec<u8> {
+        let mut report = message[..self.length()].to_vec();
+        report[0] = self.report_id();
+        report
     }
 }
 
+/// Works out which report format to use for a device, and which format (if any) to fall back to if
+/// writes fail, based on the report IDs in the device's HID report descriptor.
+fn detect_report_types(transport: &dyn Transport) -> (ReportType, Option<ReportType>) {
+    let mut descriptor = [0u8; MAXIMUM_REPORT_DESCRIPTOR_SIZE];
+    let report_ids = match transport.report_descriptor(&mut descriptor) {
+        Ok(length) => report_ids_from_descriptor(&descriptor[..length]),
+        Err(_) => Vec::new(),
+    };
+
+    report_types_from_ids(&report_ids)
+}
+
+/// Works out which report format to use for a device, and which format (if any) to fall back to,
+/// from the report IDs the device declares.
+fn report_types_from_ids(report_ids: &[u8]) -> (ReportType, Option<ReportType>) {
+    let has_short = report_ids.contains(&SHORT_REPORT_ID);
+    let has_long = report_ids.contains(&LONG_REPORT_ID);
+
+    match (has_short, has_long) {
+        (true, false) => (ReportType::Short, None),
+        (false, true) => (ReportType::Long, None),
+        _ => (ReportType::Long, Some(ReportType::Long.other())),
+    }
+}
+
+/// Extracts all of the report IDs declared in a HID report descriptor.
+fn report_ids_from_descriptor(descriptor: &[u8]) -> Vec<u8> {
+    const REPORT_ID_ITEM: u8 = 0x84;
+    const LONG_ITEM: u8 = 0xfe;
+
+    let mut report_ids = Vec::new();
+    let mut index = 0;
+
+    while index < descriptor.len() {
+        let prefix = descriptor[index];
+
+        if prefix == LONG_ITEM {
+            let data_size = descriptor.get(index + 1).copied().unwrap_or(0) as usize;
+            index += 3 + data_size;
+            continue;
+        }
+
+        let data_size = match prefix & 0x03 {
+            3 => 4,
+            size => size as usize,
+        };
+
+        if prefix & 0xfc == REPORT_ID_ITEM && data_size == 1 {
+            if let Some(report_id) = descriptor.get(index + 1) {
+                report_ids.push(*report_id);
+            }
+        }
+
+        index += 1 + data_size;
+    }
+
+    report_ids
+}
+
+/// The USB vendor ID shared by all supported devices.
+pub const VENDOR_ID: u16 = 0x046d;
+/// The HID usage page of the interface which supported devices are controlled through.
+pub const USAGE_PAGE: u16 = 0xff43;
+
+/// The USB product IDs of all supported devices, along with the model each one belongs to.
+pub const PRODUCT_IDS: [(u16, DeviceType); 4] = [
+    (0xc900, DeviceType::LitraGlow),
+    (0xc901, DeviceType::LitraBeam),
+    (0xb901, DeviceType::LitraBeam),
+    (0xc903, DeviceType::LitraBeamLX),
+];
+
+const SHORT_REPORT_ID: u8 = 0x10;
+const LONG_REPORT_ID: u8 = 0x11;
+
+/// The software ID we include in requests, which devices echo back in their responses.
+const SOFTWARE_ID: u8 = 0x01;
+const ROOT_FEATURE_INDEX: u8 = 0x00;
+/// The feature index used by devices to report that a request failed.
+const ERROR_FEATURE_INDEX: u8 = 0xff;
+const DEVICE_INFORMATION_FEATURE_ID: u16 = 0x0003;
+/// The number of reports to read while waiting for a response before giving up.
+const MAXIMUM_UNRELATED_RESPONSES: usize = 10;
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+/// The largest HID report descriptor allowed by the HID specification.
+const MAXIMUM_REPORT_DESCRIPTOR_SIZE: usize = 4096;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_litra_interface(device_info: &DeviceInfo) -> bool {
+    device_info.vendor_id() == VENDOR_ID && device_info.usage_page() == USAGE_PAGE
+}
+
+// The root feature's `getFeature` function, and the device information feature's `getFwInfo`.
+const GET_FEATURE_FUNCTION: u8 = 0x00;
+const GET_FIRMWARE_INFO_FUNCTION: u8 = 0x01;
+
+// The illumination feature's getter functions. Responses to these are matched to the request, so
+// that responses to earlier writes still waiting to be read are skipped.
+const GET_ON_FUNCTION: u8 = 0x00;
+const GET_BRIGHTNESS_IN_LUMEN_FUNCTION: u8 = 0x03;
+const GET_TEMPERATURE_IN_KELVIN_FUNCTION: u8 = 0x08;
+
+const TEMPERATURE_STEP_IN_KELVIN: u16 = 100;
+const BRIGHTNESS_STEP_IN_LUMEN: u16 = 1;
+/// The Litra Beam LX's RGB back light is split into seven zones.
+const LITRA_BEAM_LX_RGB_ZONES: u8 = 7;
+
+fn generate_set_on_bytes(feature_index: u8, on: bool) -> [u8; 20] {
+    let on_byte = if on { 0x01 } else { 0x00 };
+    generate_message_bytes(feature_index, 0x1c, [on_byte, 0x00])
+}
+
+fn generate_set_brightness_in_lumen_bytes(feature_index: u8, brightness_in_lumen: u16) -> [u8; 20] {
+    generate_message_bytes(feature_index, 0x4c, brightness_in_lumen.to_be_bytes())
+}
+
 fn generate_set_temperature_in_kelvin_bytes(
-    device_type: &DeviceType,
+    feature_index: u8,
     temperature_in_kelvin: u16,
 ) -> [u8; 20] {
-    let temperature_bytes = temperature_in_kelvin.to_be_bytes();
-
-    match device_type {
-        DeviceType::LitraGlow | DeviceType::LitraBeam => [
-            0x11,
-            0xff,
-            0x04,
-            0x9c,
-            temperature_bytes[0],
-            temperature_bytes[1],
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-        ],
-        DeviceType::LitraBeamLX => [
-            0x11,
-            0xff,
-            0x06,
-            0x9c,
-            temperature_bytes[0],
-            temperature_bytes[1],
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-            0x00,
-        ],
-    }
+    generate_message_bytes(feature_index, 0x9c, temperature_in_kelvin.to_be_bytes())
+}
+
+/// Generates a HID++ request for a function of a feature, which the device will respond to.
+fn generate_request_bytes(feature_index: u8, function: u8, parameters: &[u8]) -> [u8; 20] {
+    let mut message = [0x00; 20];
+    message[0] = LONG_REPORT_ID;
+    message[1] = 0xff;
+    message[2] = feature_index;
+    message[3] = (function << 4) | SOFTWARE_ID;
+    message[4..4 + parameters.len()].copy_from_slice(parameters);
+    message
+}
+
+/// Returns whether a report is the response to a request, or an error reported for it.
+fn is_response_to(request: &[u8; 20], report: &[u8; 20]) -> bool {
+    let feature_index = request[2];
+    let function_and_software_id = request[3];
+
+    let is_error = report[2] == ERROR_FEATURE_INDEX
+        && report[3] == feature_index
+        && report[4] == function_and_software_id;
+    is_error || (report[2] == feature_index && report[3] == function_and_software_id)
+}
+
+fn generate_message_bytes(feature_index: u8, function: u8, parameters: [u8; 2]) -> [u8; 20] {
+    let mut message = [0x00; 20];
+    message[0] = LONG_REPORT_ID;
+    message[1] = 0xff;
+    message[2] = feature_index;
+    message[3] = function;
+    message[4..6].copy_from_slice(&parameters);
+    message
 }
@@ -0,0 +1,367 @@
+//! Simulated devices, for testing code which uses this crate without real hardware.
+//!
+//! This module is only available with the `mock` feature. A [`MockDevice`] answers the same
+//! HID++ requests as a real device of its model, and records every report written to it so that
+//! tests can check exactly what was sent.
+//!
+//! Simulated devices can also be added to a real [`Litra`] context with
+//! [`Litra::add_simulated_device`], so that they are listed alongside any connected devices.
+//!
+//! ```
+//! use litra::mock::MockLitra;
+//! use litra::DeviceType;
+//!
+//! let mut context = MockLitra::new();
+//! let device = context.add_device(DeviceType::LitraGlow, "2325FE7044P8");
+//!
+//! let device_handle = device.open();
+//! device_handle.set_on(true).unwrap();
+//! device_handle.set_brightness_in_lumen(100).unwrap();
+//!
+//! assert!(device.is_on());
+//! assert_eq!(device.brightness_in_lumen(), 100);
+//! assert_eq!(device.written_reports().len(), 2);
+//!
+//! // Simulate someone pressing the device's buttons
+//! device.set_brightness_in_lumen(150);
+//! assert_eq!(device_handle.brightness_in_lumen().unwrap(), 150);
+//! ```
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Litra;
+use crate::{
+    DeviceDescriptor, DeviceError, DeviceHandle, DeviceResult, DeviceType, Transport,
+    DEVICE_INFORMATION_FEATURE_ID, ERROR_FEATURE_INDEX, LONG_REPORT_ID, ROOT_FEATURE_INDEX,
+};
+use std::collections::VecDeque;
+#[cfg(not(target_arch = "wasm32"))]
+use std::ffi::{CStr, CString};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// The feature index of the HID++ device information feature on simulated devices.
+const DEVICE_INFORMATION_FEATURE_INDEX: u8 = 0x01;
+/// The firmware which simulated devices report, as returned by the device information feature.
+const FIRMWARE_INFO: [u8; 8] = [0x00, b'R', b'Q', b'M', 0x01, 0x00, 0x00, 0x01];
+
+// HID++ 2.0 error codes.
+const INVALID_FEATURE_INDEX_ERROR: u8 = 0x06;
+const INVALID_FUNCTION_ID_ERROR: u8 = 0x07;
+
+/// A set of simulated devices, standing in for a [`Litra`](crate::Litra) context.
+#[derive(Debug, Default)]
+pub struct MockLitra {
+    devices: Vec<MockDevice>,
+}
+
+impl MockLitra {
+    /// Creates a context without any devices.
+    #[must_use]
+    pub fn new() -> Self {
+        MockLitra::default()
+    }
+
+    /// Adds a simulated device, returning it so that it can be opened and inspected.
+    pub fn add_device(
+        &mut self,
+        device_type: DeviceType,
+        serial_number: impl Into<String>,
+    ) -> MockDevice {
+        let device = MockDevice::new(device_type, serial_number);
+        self.devices.push(device.clone());
+        device
+    }
+
+    /// Returns the simulated devices, in the order they were added.
+    #[must_use]
+    pub fn devices(&self) -> &[MockDevice] {
+        &self.devices
+    }
+
+    /// Returns the simulated device with the given serial number.
+    #[must_use]
+    pub fn device(&self, serial_number: &str) -> Option<&MockDevice> {
+        self.devices
+            .iter()
+            .find(|device| device.serial_number == serial_number)
+    }
+}
+
+/// A simulated device. Clones share the same state, so a device can be inspected while handles
+/// opened from it are in use.
+#[derive(Debug, Clone)]
+pub struct MockDevice {
+    serial_number: String,
+    /// The path the device is listed with when added to a [`Litra`] context.
+    #[cfg(not(target_arch = "wasm32"))]
+    path: CString,
+    descriptor: DeviceDescriptor,
+    state: Arc<Mutex<MockState>>,
+    /// The connection a handle was opened on, so that handles opened before the device was
+    /// reconnected stop working, like they do for real devices.
+    connection: u32,
+}
+
+#[derive(Debug)]
+struct MockState {
+    is_connected: bool,
+    /// Incremented each time the device is reconnected.
+    connection: u32,
+    is_on: bool,
+    brightness_in_lumen: u16,
+    /// The internal step which brightness values are rounded down to, if any.
+    brightness_rounding_in_lumen: Option<u16>,
+    temperature_in_kelvin: u16,
+    written_reports: Vec<Vec<u8>>,
+    pending_responses: VecDeque<[u8; 20]>,
+}
+
+impl MockDevice {
+    /// Creates a simulated device, which starts off at its minimum brightness and temperature.
+    #[must_use]
+    pub fn new(device_type: DeviceType, serial_number: impl Into<String>) -> Self {
+        let descriptor = DeviceDescriptor::for_device_type(device_type);
+        let serial_number = serial_number.into();
+        MockDevice {
+            #[cfg(not(target_arch = "wasm32"))]
+            path: CString::new(format!("simulated:{}", serial_number)).unwrap_or_default(),
+            serial_number,
+            descriptor,
+            state: Arc::new(Mutex::new(MockState {
+                is_connected: true,
+                connection: 0,
+                is_on: false,
+                brightness_in_lumen: descriptor.minimum_brightness_in_lumen,
+                brightness_rounding_in_lumen: None,
+                temperature_in_kelvin: descriptor.minimum_temperature_in_kelvin,
+                written_reports: Vec::new(),
+                pending_responses: VecDeque::new(),
+            })),
+            connection: 0,
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Opens the device, returning a [`DeviceHandle`] which works like one for a real device.
+    #[must_use]
+    pub fn open(&self) -> DeviceHandle {
+        let transport = MockDevice {
+            connection: self.state().connection,
+            ..self.clone()
+        };
+        DeviceHandle::from_transport(transport, self.descriptor)
+    }
+
+    /// The serial number of the device.
+    #[must_use]
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
+
+    /// The model of the device.
+    #[must_use]
+    pub fn device_type(&self) -> DeviceType {
+        self.descriptor.device_type
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn path(&self) -> &CStr {
+        &self.path
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn descriptor(&self) -> DeviceDescriptor {
+        self.descriptor
+    }
+
+    /// Returns whether the device is connected.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.state().is_connected
+    }
+
+    /// Disconnects the device, as if it had been unplugged. Handles opened from it fail with
+    /// [`DeviceError::NotConnected`], and it isn't listed by a [`Litra`](crate::Litra) context.
+    pub fn disconnect(&self) {
+        self.state().is_connected = false;
+    }
+
+    /// Connects the device again, as if it had been plugged back in. Like real devices, handles
+    /// opened before it was disconnected keep failing, so it needs to be opened again.
+    pub fn reconnect(&self) {
+        let mut state = self.state();
+        state.is_connected = true;
+        state.connection += 1;
+    }
+
+    /// Returns whether the device is on.
+    #[must_use]
+    pub fn is_on(&self) -> bool {
+        self.state().is_on
+    }
+
+    /// Returns the device's brightness in Lumen.
+    #[must_use]
+    pub fn brightness_in_lumen(&self) -> u16 {
+        self.state().brightness_in_lumen
+    }
+
+    /// Returns the device's color temperature in Kelvin.
+    #[must_use]
+    pub fn temperature_in_kelvin(&self) -> u16 {
+        self.state().temperature_in_kelvin
+    }
+
+    /// Turns the device on or off, as if its power button had been pressed.
+    pub fn set_on(&self, on: bool) {
+        self.state().is_on = on;
+    }
+
+    /// Sets the device's brightness, as if its buttons had been used.
+    pub fn set_brightness_in_lumen(&self, brightness_in_lumen: u16) {
+        self.state().brightness_in_lumen = brightness_in_lumen;
+    }
+
+    /// Makes the device round brightness values it's sent down to a multiple of `step_in_lumen`
+    /// above its minimum, like models which store brightness in coarser internal steps. Brightness
+    /// then reads back differently from what was set.
+    pub fn set_brightness_rounding_in_lumen(&self, step_in_lumen: u16) {
+        self.state().brightness_rounding_in_lumen = Some(step_in_lumen);
+    }
+
+    /// Sets the device's color temperature, as if its buttons had been used.
+    pub fn set_temperature_in_kelvin(&self, temperature_in_kelvin: u16) {
+        self.state().temperature_in_kelvin = temperature_in_kelvin;
+    }
+
+    /// Returns every report written to the device, including its report ID, in the order they
+    /// were written.
+    #[must_use]
+    pub fn written_reports(&self) -> Vec<Vec<u8>> {
+        self.state().written_reports.clone()
+    }
+
+    /// Forgets the reports written to the device so far.
+    pub fn clear_written_reports(&self) {
+        self.state().written_reports.clear();
+    }
+
+    /// Fails if the device is disconnected, or has been reconnected since this handle was opened.
+    fn check_connected(&self, state: &MockState) -> DeviceResult<()> {
+        if state.is_connected && state.connection == self.connection {
+            Ok(())
+        } else {
+            Err(DeviceError::NotConnected)
+        }
+    }
+
+    /// Handles a HID++ request, returning the response to send, if any. Like real devices, the
+    /// illumination feature's setters don't need a response to be read.
+    fn handle_request(&self, state: &mut MockState, request: &[u8; 20]) -> Option<[u8; 20]> {
+        let feature_index = request[2];
+        let function = request[3] >> 4;
+        let parameter = u16::from_be_bytes([request[4], request[5]]);
+
+        let response = |parameters: &[u8]| {
+            let mut response = [0x00; 20];
+            response[..4].copy_from_slice(&request[..4]);
+            response[4..4 + parameters.len()].copy_from_slice(parameters);
+            response
+        };
+        let error = |error_code: u8| {
+            let mut response = [0x00; 20];
+            response[..2].copy_from_slice(&request[..2]);
+            response[2] = ERROR_FEATURE_INDEX;
+            response[3] = feature_index;
+            response[4] = request[3];
+            response[5] = error_code;
+            response
+        };
+
+        match (feature_index, function) {
+            (ROOT_FEATURE_INDEX, 0x00) => {
+                let found_feature_index = if parameter == DEVICE_INFORMATION_FEATURE_ID {
+                    DEVICE_INFORMATION_FEATURE_INDEX
+                } else {
+                    0x00
+                };
+                Some(response(&[found_feature_index]))
+            }
+            (DEVICE_INFORMATION_FEATURE_INDEX, 0x01) => Some(response(&FIRMWARE_INFO)),
+            (DEVICE_INFORMATION_FEATURE_INDEX, _) => Some(error(INVALID_FUNCTION_ID_ERROR)),
+            (index, function) if index == self.descriptor.feature_index => match function {
+                0x00 => Some(response(&[u8::from(state.is_on)])),
+                0x01 => {
+                    state.is_on = request[4] == 0x01;
+                    None
+                }
+                0x03 => Some(response(&state.brightness_in_lumen.to_be_bytes())),
+                0x04 => {
+                    state.brightness_in_lumen = match state.brightness_rounding_in_lumen {
+                        Some(step_in_lumen) => {
+                            let minimum = self.descriptor.minimum_brightness_in_lumen;
+                            let above_minimum = parameter.saturating_sub(minimum);
+                            minimum + above_minimum - above_minimum % step_in_lumen.max(1)
+                        }
+                        None => parameter,
+                    };
+                    None
+                }
+                0x08 => Some(response(&state.temperature_in_kelvin.to_be_bytes())),
+                0x09 => {
+                    state.temperature_in_kelvin = parameter;
+                    None
+                }
+                _ => Some(error(INVALID_FUNCTION_ID_ERROR)),
+            },
+            _ => Some(error(INVALID_FEATURE_INDEX_ERROR)),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Litra {
+    /// Adds a simulated device, which is listed by [`Litra::get_connected_devices`] after any
+    /// connected devices and can be opened like them. The device is shared with any clones of
+    /// `device`, so it can still be inspected.
+    pub fn add_simulated_device(&mut self, device: MockDevice) {
+        self.simulated_devices.push(device);
+    }
+}
+
+impl Transport for MockDevice {
+    fn write(&self, report: &[u8]) -> DeviceResult<()> {
+        let mut state = self.state();
+        self.check_connected(&state)?;
+        state.written_reports.push(report.to_vec());
+
+        // Short reports are handled like long ones, since their parameters are just truncated
+        let mut request = [0x00; 20];
+        let length = report.len().min(request.len());
+        request[..length].copy_from_slice(&report[..length]);
+        request[0] = LONG_REPORT_ID;
+        if let Some(response) = self.handle_request(&mut state, &request) {
+            state.pending_responses.push_back(response);
+        }
+        Ok(())
+    }
+
+    fn read_timeout(&self, report: &mut [u8], _timeout: Duration) -> DeviceResult<usize> {
+        let mut state = self.state();
+        self.check_connected(&state)?;
+        // There is nothing to wait for, so a missing response times out straight away
+        let Some(response) = state.pending_responses.pop_front() else {
+            return Ok(0);
+        };
+        let length = report.len().min(response.len());
+        report[..length].copy_from_slice(&response[..length]);
+        Ok(length)
+    }
+
+    fn serial_number(&self) -> DeviceResult<Option<String>> {
+        Ok(Some(self.serial_number.clone()))
+    }
+}
@@ -0,0 +1,117 @@
+//! Node.js bindings for controlling Logitech Litra lights from JavaScript and TypeScript, built
+//! with [napi-rs](https://napi.rs).
+//!
+//! This module is only available with the `node` feature. To build a native addon, run:
+//!
+//! ```sh
+//! cargo rustc --release --lib --features node --crate-type cdylib
+//! ```
+//!
+//! Then copy the resulting library (e.g. `target/release/liblitra.so` on Linux) to `litra.node`
+//! and load it with `require("./litra.node")`.
+//!
+//! Every function takes an optional serial number as its last argument. If it is omitted, the
+//! first connected device is used.
+
+use crate::{Device, DeviceHandle, Litra};
+use napi::{Error, Result};
+use napi_derive::napi;
+
+/// A connected device and its current state.
+#[napi(object)]
+#[derive(Debug)]
+pub struct LitraDevice {
+    /// The serial number of the device.
+    pub serial_number: String,
+    /// The model of the device, e.g. `Litra Glow`.
+    pub device_type: String,
+    /// Whether the device is on.
+    pub is_on: bool,
+    /// The device's current brightness in Lumen.
+    pub brightness_in_lumen: u16,
+    /// The device's current color temperature in Kelvin.
+    pub temperature_in_kelvin: u16,
+    /// The minimum brightness supported by the device in Lumen.
+    pub minimum_brightness_in_lumen: u16,
+    /// The maximum brightness supported by the device in Lumen.
+    pub maximum_brightness_in_lumen: u16,
+    /// The minimum color temperature supported by the device in Kelvin.
+    pub minimum_temperature_in_kelvin: u16,
+    /// The maximum color temperature supported by the device in Kelvin.
+    pub maximum_temperature_in_kelvin: u16,
+}
+
+/// Lists the connected devices along with their current state. Devices which can't be opened or
+/// queried are skipped.
+#[napi]
+pub fn list_devices() -> Result<Vec<LitraDevice>> {
+    let context = Litra::new().map_err(to_napi_error)?;
+    Ok(context
+        .get_connected_devices()
+        .filter_map(|device| {
+            let device_handle = device.open(&context).ok()?;
+            Some(LitraDevice {
+                serial_number: device.serial_number().unwrap_or("").to_string(),
+                device_type: device.device_type().to_string(),
+                is_on: device_handle.is_on().ok()?,
+                brightness_in_lumen: device_handle.brightness_in_lumen().ok()?,
+                temperature_in_kelvin: device_handle.temperature_in_kelvin().ok()?,
+                minimum_brightness_in_lumen: device_handle.minimum_brightness_in_lumen(),
+                maximum_brightness_in_lumen: device_handle.maximum_brightness_in_lumen(),
+                minimum_temperature_in_kelvin: device_handle.minimum_temperature_in_kelvin(),
+                maximum_temperature_in_kelvin: device_handle.maximum_temperature_in_kelvin(),
+            })
+        })
+        .collect())
+}
+
+/// Turns the device on or off.
+#[napi]
+pub fn set_on(on: bool, serial_number: Option<String>) -> Result<()> {
+    with_device(serial_number, |device_handle| device_handle.set_on(on))
+}
+
+/// Toggles the device on or off.
+#[napi]
+pub fn toggle(serial_number: Option<String>) -> Result<()> {
+    with_device(serial_number, |device_handle| {
+        device_handle.set_on(!device_handle.is_on()?)
+    })
+}
+
+/// Sets the device's brightness in Lumen.
+#[napi]
+pub fn set_brightness(brightness_in_lumen: u16, serial_number: Option<String>) -> Result<()> {
+    with_device(serial_number, |device_handle| {
+        device_handle.set_brightness_in_lumen(brightness_in_lumen)
+    })
+}
+
+/// Sets the device's color temperature in Kelvin.
+#[napi]
+pub fn set_temperature(temperature_in_kelvin: u16, serial_number: Option<String>) -> Result<()> {
+    with_device(serial_number, |device_handle| {
+        device_handle.set_temperature_in_kelvin(temperature_in_kelvin)
+    })
+}
+
+fn with_device<T>(
+    serial_number: Option<String>,
+    operation: impl FnOnce(&DeviceHandle) -> crate::DeviceResult<T>,
+) -> Result<T> {
+    let context = Litra::new().map_err(to_napi_error)?;
+    let device = context
+        .get_connected_devices()
+        .find(|device: &Device<'_>| {
+            serial_number
+                .as_deref()
+                .is_none_or(|expected| device.serial_number() == Some(expected))
+        })
+        .ok_or_else(|| Error::from_reason("Device not found."))?;
+    let device_handle = device.open(&context).map_err(to_napi_error)?;
+    operation(&device_handle).map_err(to_napi_error)
+}
+
+fn to_napi_error(error: crate::DeviceError) -> Error {
+    Error::from_reason(error.to_string())
+}
@@ -0,0 +1,443 @@
+//! Controlling devices from a web browser with the [WebHID API], for applications compiled to
+//! WebAssembly.
+//!
+//! This module is only available with the `wasm` feature. WebHID is still an unstable API in
+//! `web-sys`, so applications must be built with `RUSTFLAGS=--cfg=web_sys_unstable_apis`.
+//! Browsers only let a page use the devices which the user has picked, so devices are found with
+//! [`request_devices`], which has to be called in response to a user gesture like a click, or with
+//! [`get_devices`] for devices which the user has already picked.
+//!
+//! Browsers can't block while waiting for a device to respond, so [`WebHidDeviceHandle`] is async,
+//! but otherwise it talks to devices exactly like [`DeviceHandle`](crate::DeviceHandle).
+//!
+//! ```no_run
+//! # async fn example() -> litra::DeviceResult<()> {
+//! use litra::webhid;
+//!
+//! for device_handle in webhid::request_devices().await? {
+//!     device_handle.set_on(true).await?;
+//!     device_handle.set_brightness_in_lumen(100).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [WebHID API]: https://developer.mozilla.org/en-US/docs/Web/API/WebHID_API
+
+use crate::{
+    generate_request_bytes, generate_set_brightness_in_lumen_bytes, generate_set_on_bytes,
+    generate_set_temperature_in_kelvin_bytes, is_response_to, report_types_from_ids, Capabilities,
+    DeviceDescriptor, DeviceError, DeviceResult, DeviceType, FirmwareVersion, RawResponse,
+    ReportType, DEFAULT_TIMEOUT, DEVICE_INFORMATION_FEATURE_ID, GET_BRIGHTNESS_IN_LUMEN_FUNCTION,
+    GET_FEATURE_FUNCTION, GET_FIRMWARE_INFO_FUNCTION, GET_ON_FUNCTION,
+    GET_TEMPERATURE_IN_KELVIN_FUNCTION, MAXIMUM_UNRELATED_RESPONSES, ROOT_FEATURE_INDEX,
+    USAGE_PAGE, VENDOR_ID,
+};
+use js_sys::{Array, Function, Promise};
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Hid, HidDevice, HidDeviceFilter, HidDeviceRequestOptions, HidInputReportEvent};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &Function, timeout: i32);
+}
+
+/// Asks the user to pick the devices the page may use, and opens the supported ones. This has to
+/// be called in response to a user gesture, like a click.
+pub async fn request_devices() -> DeviceResult<Vec<WebHidDeviceHandle>> {
+    let filter = HidDeviceFilter::new();
+    filter.set_vendor_id(VENDOR_ID.into());
+    filter.set_usage_page(USAGE_PAGE);
+    let options = HidDeviceRequestOptions::new(&[filter]);
+
+    let devices = JsFuture::from(hid()?.request_device(&options))
+        .await
+        .map_err(webhid_error)?;
+    open_devices(devices).await
+}
+
+/// Opens the supported devices which the user has already allowed the page to use.
+pub async fn get_devices() -> DeviceResult<Vec<WebHidDeviceHandle>> {
+    let devices = JsFuture::from(hid()?.get_devices())
+        .await
+        .map_err(webhid_error)?;
+    open_devices(devices).await
+}
+
+async fn open_devices(devices: Array<HidDevice>) -> DeviceResult<Vec<WebHidDeviceHandle>> {
+    let mut device_handles = Vec::new();
+    for device in devices {
+        if device.vendor_id() != VENDOR_ID || !has_litra_interface(&device) {
+            continue;
+        }
+        if let Some(descriptor) = DeviceDescriptor::for_product_id(device.product_id()) {
+            device_handles.push(WebHidDeviceHandle::open(device, descriptor).await?);
+        }
+    }
+    Ok(device_handles)
+}
+
+/// The handle of an opened device, with async versions of the methods of
+/// [`DeviceHandle`](crate::DeviceHandle).
+///
+/// The device stays open while the handle exists, and can be used by other pages at the same time.
+#[derive(Debug)]
+pub struct WebHidDeviceHandle {
+    device: HidDevice,
+    descriptor: DeviceDescriptor,
+    report_type: Cell<ReportType>,
+    fallback_report_type: Option<ReportType>,
+    firmware_version: OnceCell<FirmwareVersion>,
+    timeout: Duration,
+    reports: Rc<RefCell<InputReports>>,
+    _on_input_report: Closure<dyn FnMut(HidInputReportEvent)>,
+}
+
+/// The reports received from a device which haven't been read yet, and the function to call when
+/// the next one arrives, if a read is waiting for one.
+#[derive(Debug, Default)]
+struct InputReports {
+    reports: VecDeque<[u8; 20]>,
+    on_report: Option<Function>,
+}
+
+impl WebHidDeviceHandle {
+    /// Opens a device which was picked by the user, using a descriptor which describes how to
+    /// communicate with it. For supported models, this can be found with
+    /// [`DeviceDescriptor::for_product_id`].
+    ///
+    /// The HID++ report format used to talk to the device is detected from the reports which the
+    /// browser says it supports.
+    pub async fn open(device: HidDevice, descriptor: DeviceDescriptor) -> DeviceResult<Self> {
+        if !device.opened() {
+            JsFuture::from(device.open()).await.map_err(webhid_error)?;
+        }
+
+        let reports = Rc::new(RefCell::new(InputReports::default()));
+        let on_input_report = Closure::<dyn FnMut(HidInputReportEvent)>::new({
+            let reports = Rc::clone(&reports);
+            move |event: HidInputReportEvent| {
+                // WebHID passes the report ID separately from the report's data
+                let mut report = [0x00; 20];
+                report[0] = event.report_id();
+                let data = event.data();
+                for (index, byte) in report[1..].iter_mut().enumerate() {
+                    if index >= data.byte_length() {
+                        break;
+                    }
+                    *byte = data.get_uint8(index);
+                }
+
+                let mut reports = reports.borrow_mut();
+                reports.reports.push_back(report);
+                if let Some(on_report) = reports.on_report.take() {
+                    let _ = on_report.call0(&JsValue::NULL);
+                }
+            }
+        });
+        device.set_oninputreport(Some(on_input_report.as_ref().unchecked_ref()));
+
+        let (report_type, fallback_report_type) =
+            report_types_from_ids(&output_report_ids(&device));
+        Ok(WebHidDeviceHandle {
+            device,
+            descriptor,
+            report_type: Cell::new(report_type),
+            fallback_report_type,
+            firmware_version: OnceCell::new(),
+            timeout: DEFAULT_TIMEOUT,
+            reports,
+            _on_input_report: on_input_report,
+        })
+    }
+
+    /// The model of the device.
+    #[must_use]
+    pub fn device_type(&self) -> DeviceType {
+        self.descriptor.device_type
+    }
+
+    /// The descriptor used to communicate with the device.
+    #[must_use]
+    pub fn descriptor(&self) -> &DeviceDescriptor {
+        &self.descriptor
+    }
+
+    /// Returns what the device supports.
+    #[must_use]
+    pub fn capabilities(&self) -> Capabilities {
+        self.descriptor.capabilities()
+    }
+
+    /// The WebHID device.
+    #[must_use]
+    pub fn hid_device(&self) -> &HidDevice {
+        &self.device
+    }
+
+    /// Sets how long to wait for the device to respond before failing with
+    /// [`DeviceError::Timeout`]. Defaults to 1 second.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Queries the current power status of the device. Returns `true` if the device is currently on.
+    pub async fn is_on(&self) -> DeviceResult<bool> {
+        let response = self
+            .request(self.descriptor.feature_index, GET_ON_FUNCTION, &[])
+            .await?;
+
+        Ok(response[4] == 1)
+    }
+
+    /// Sets the power status of the device. Turns the device on if `true` is passed and turns it
+    /// of on `false`.
+    pub async fn set_on(&self, on: bool) -> DeviceResult<()> {
+        let message = generate_set_on_bytes(self.descriptor.feature_index, on);
+
+        self.write_message(&message).await
+    }
+
+    /// Queries the device's current brightness in Lumen.
+    pub async fn brightness_in_lumen(&self) -> DeviceResult<u16> {
+        let response = self
+            .request(
+                self.descriptor.feature_index,
+                GET_BRIGHTNESS_IN_LUMEN_FUNCTION,
+                &[],
+            )
+            .await?;
+
+        Ok(u16::from_be_bytes([response[4], response[5]]))
+    }
+
+    /// Sets the device's brightness in Lumen.
+    pub async fn set_brightness_in_lumen(&self, brightness_in_lumen: u16) -> DeviceResult<()> {
+        if !self
+            .descriptor
+            .is_valid_brightness_in_lumen(brightness_in_lumen)
+        {
+            return Err(DeviceError::InvalidBrightness(brightness_in_lumen));
+        }
+
+        let message = generate_set_brightness_in_lumen_bytes(
+            self.descriptor.feature_index,
+            brightness_in_lumen,
+        );
+
+        self.write_message(&message).await
+    }
+
+    /// Queries the device's current color temperature in Kelvin.
+    pub async fn temperature_in_kelvin(&self) -> DeviceResult<u16> {
+        let response = self
+            .request(
+                self.descriptor.feature_index,
+                GET_TEMPERATURE_IN_KELVIN_FUNCTION,
+                &[],
+            )
+            .await?;
+
+        Ok(u16::from_be_bytes([response[4], response[5]]))
+    }
+
+    /// Sets the device's color temperature in Kelvin.
+    pub async fn set_temperature_in_kelvin(&self, temperature_in_kelvin: u16) -> DeviceResult<()> {
+        if !self
+            .descriptor
+            .is_valid_temperature_in_kelvin(temperature_in_kelvin)
+        {
+            return Err(DeviceError::InvalidTemperature(temperature_in_kelvin));
+        }
+
+        let message = generate_set_temperature_in_kelvin_bytes(
+            self.descriptor.feature_index,
+            temperature_in_kelvin,
+        );
+
+        self.write_message(&message).await
+    }
+
+    /// Queries the device's firmware version using the HID++ device information feature. The
+    /// result is cached for the lifetime of the handle.
+    pub async fn firmware_version(&self) -> DeviceResult<FirmwareVersion> {
+        if let Some(firmware_version) = self.firmware_version.get() {
+            return Ok(firmware_version.clone());
+        }
+
+        let response = self
+            .request(
+                ROOT_FEATURE_INDEX,
+                GET_FEATURE_FUNCTION,
+                &DEVICE_INFORMATION_FEATURE_ID.to_be_bytes(),
+            )
+            .await?;
+        let feature_index = match response[4] {
+            0x00 => return Err(DeviceError::Unsupported),
+            feature_index => feature_index,
+        };
+        // Entity 0 is the main application firmware
+        let response = self
+            .request(feature_index, GET_FIRMWARE_INFO_FUNCTION, &[0x00])
+            .await?;
+
+        let firmware_version = FirmwareVersion::from_response(&response);
+        Ok(self
+            .firmware_version
+            .get_or_init(|| firmware_version)
+            .clone())
+    }
+
+    /// Sends a HID++ request and waits for the matching response, skipping any unrelated reports
+    /// received in the meantime. Error responses are mapped to [`DeviceError::Unsupported`].
+    async fn request(
+        &self,
+        feature_index: u8,
+        function: u8,
+        parameters: &[u8],
+    ) -> DeviceResult<[u8; 20]> {
+        let message = generate_request_bytes(feature_index, function, parameters);
+        self.write_message(&message).await?;
+
+        for _ in 0..MAXIMUM_UNRELATED_RESPONSES {
+            let Some(report) = self.read_report().await else {
+                return Err(DeviceError::Timeout);
+            };
+
+            if is_response_to(&message, &report) {
+                let response = RawResponse { report };
+                if response.error_code().is_some() {
+                    return Err(DeviceError::Unsupported);
+                }
+                return Ok(report);
+            }
+        }
+
+        Err(DeviceError::Unsupported)
+    }
+
+    /// Waits up to the handle's timeout for the next report from the device. Returns `None` if
+    /// there wasn't one.
+    async fn read_report(&self) -> Option<[u8; 20]> {
+        if let Some(report) = self.reports.borrow_mut().reports.pop_front() {
+            return Some(report);
+        }
+
+        // The promise is resolved by whichever comes first, a report or the timeout
+        let timeout_in_milliseconds = i32::try_from(self.timeout.as_millis()).unwrap_or(i32::MAX);
+        let reports = Rc::clone(&self.reports);
+        let wait = Promise::new(&mut |resolve, _reject| {
+            set_timeout(&resolve, timeout_in_milliseconds);
+            reports.borrow_mut().on_report = Some(resolve);
+        });
+        let _ = JsFuture::from(wait).await;
+
+        let mut reports = self.reports.borrow_mut();
+        reports.on_report = None;
+        reports.reports.pop_front()
+    }
+
+    /// Writes a HID++ message using the current report format. If the write fails and the device
+    /// may support the other report format, the message is retried in that format, which is then
+    /// used for all subsequent messages.
+    async fn write_message(&self, message: &[u8; 20]) -> DeviceResult<()> {
+        let report_type = self.report_type.get();
+
+        match self.send_report(report_type, message).await {
+            Ok(()) => Ok(()),
+            Err(error) => match self.fallback_report_type {
+                Some(fallback_report_type)
+                    if fallback_report_type.can_encode(message)
+                        && self
+                            .send_report(fallback_report_type, message)
+                            .await
+                            .is_ok() =>
+                {
+                    self.report_type.set(fallback_report_type);
+                    Ok(())
+                }
+                _ => Err(error),
+            },
+        }
+    }
+
+    async fn send_report(&self, report_type: ReportType, message: &[u8; 20]) -> DeviceResult<()> {
+        // WebHID takes the report ID separately from the report's data
+        let mut report = report_type.encode(message);
+        let sent = self
+            .device
+            .send_report_with_u8_slice(report[0], &mut report[1..])
+            .map_err(webhid_error)?;
+        JsFuture::from(sent).await.map_err(webhid_error)?;
+        Ok(())
+    }
+}
+
+impl Drop for WebHidDeviceHandle {
+    fn drop(&mut self) {
+        // The listener can't be called once its closure has been dropped
+        self.device.set_oninputreport(None);
+    }
+}
+
+/// Returns the browser's WebHID interface, or [`DeviceError::Unsupported`] if the browser doesn't
+/// support WebHID or the code isn't running in a window.
+fn hid() -> DeviceResult<Hid> {
+    let window = web_sys::window().ok_or(DeviceError::Unsupported)?;
+    let navigator = window.navigator();
+    if !js_sys::Reflect::has(&navigator, &JsValue::from_str("hid")).unwrap_or(false) {
+        return Err(DeviceError::Unsupported);
+    }
+    Ok(navigator.hid())
+}
+
+/// Returns whether the device has the HID++ interface which supported devices are controlled
+/// through.
+fn has_litra_interface(device: &HidDevice) -> bool {
+    device
+        .collections()
+        .iter()
+        .any(|collection| collection.get_usage_page() == Some(USAGE_PAGE))
+}
+
+/// Returns the IDs of the output reports which the device's HID++ interface supports.
+fn output_report_ids(device: &HidDevice) -> Vec<u8> {
+    device
+        .collections()
+        .iter()
+        .filter(|collection| collection.get_usage_page() == Some(USAGE_PAGE))
+        .filter_map(|collection| collection.get_output_reports())
+        .flat_map(|output_reports| output_reports.iter().collect::<Vec<_>>())
+        .filter_map(|output_report| output_report.get_report_id())
+        .collect()
+}
+
+/// An error reported by the browser.
+#[derive(Debug)]
+struct WebHidError(String);
+
+impl fmt::Display for WebHidError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for WebHidError {}
+
+fn webhid_error(error: JsValue) -> DeviceError {
+    let message = match error.dyn_ref::<js_sys::Error>() {
+        Some(error) => String::from(error.message()),
+        None => format!("{:?}", error),
+    };
+    DeviceError::TransportError(Box::new(WebHidError(message)))
+}
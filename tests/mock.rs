@@ -0,0 +1,498 @@
+//! Tests for how `DeviceHandle` talks to devices, using simulated devices from the `mock` feature.
+//! Run them with `cargo test --features mock`.
+
+use litra::mock::MockDevice;
+use litra::{
+    DeviceDescriptor, DeviceError, DeviceHandle, DeviceResult, DeviceType, Litra, RetryPolicy,
+    Transport,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const SHORT_REPORT_ID: u8 = 0x10;
+const LONG_REPORT_ID: u8 = 0x11;
+/// The feature index of the illumination feature on the Litra Beam LX.
+const ILLUMINATION_FEATURE_INDEX: u8 = 0x06;
+
+/// Report descriptors which only declare short or long HID++ reports.
+const SHORT_ONLY_DESCRIPTOR: [u8; 2] = [0x85, SHORT_REPORT_ID];
+const LONG_ONLY_DESCRIPTOR: [u8; 2] = [0x85, LONG_REPORT_ID];
+
+/// The start of a HID++ notification, which isn't a response to any of our requests.
+const NOTIFICATION: [u8; 5] = [LONG_REPORT_ID, 0xff, ILLUMINATION_FEATURE_INDEX, 0x00, 0x01];
+
+/// Wraps a simulated device to misbehave in the ways real devices and transports do.
+#[derive(Debug, Default)]
+struct FaultyTransport {
+    device: Option<MockDevice>,
+    report_descriptor: Option<&'static [u8]>,
+    /// Fails writes of long reports, like devices which only accept short reports.
+    reject_long_reports: bool,
+    /// The number of writes to fail before letting them through.
+    failing_writes: AtomicUsize,
+    /// The number of unrelated reports to return before each response.
+    unrelated_reports: usize,
+    unrelated_reports_remaining: AtomicUsize,
+}
+
+impl FaultyTransport {
+    fn new(device: &MockDevice) -> Self {
+        FaultyTransport {
+            device: Some(device.clone()),
+            ..FaultyTransport::default()
+        }
+    }
+
+    fn open(self, device_type: DeviceType) -> DeviceHandle {
+        let descriptor = litra::DeviceDescriptor::for_device_type(device_type);
+        DeviceHandle::from_transport(self, descriptor)
+    }
+
+    fn device(&self) -> &MockDevice {
+        self.device.as_ref().unwrap()
+    }
+}
+
+impl Transport for FaultyTransport {
+    fn write(&self, report: &[u8]) -> DeviceResult<()> {
+        if self.reject_long_reports && report[0] == LONG_REPORT_ID {
+            return Err(DeviceError::TransportError(
+                "long reports aren't supported".into(),
+            ));
+        }
+        if self
+            .failing_writes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failing_writes| {
+                failing_writes.checked_sub(1)
+            })
+            .is_ok()
+        {
+            return Err(DeviceError::TransportError("the device is busy".into()));
+        }
+
+        self.unrelated_reports_remaining
+            .store(self.unrelated_reports, Ordering::SeqCst);
+        self.device().write(report)
+    }
+
+    fn read_timeout(&self, report: &mut [u8], timeout: Duration) -> DeviceResult<usize> {
+        if self
+            .unrelated_reports_remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |remaining| {
+                remaining.checked_sub(1)
+            })
+            .is_ok()
+        {
+            report.fill(0x00);
+            report[..NOTIFICATION.len()].copy_from_slice(&NOTIFICATION);
+            return Ok(report.len());
+        }
+        self.device().read_timeout(report, timeout)
+    }
+
+    fn serial_number(&self) -> DeviceResult<Option<String>> {
+        Transport::serial_number(self.device())
+    }
+
+    fn report_descriptor(&self, descriptor: &mut [u8]) -> DeviceResult<usize> {
+        let report_descriptor = self.report_descriptor.ok_or(DeviceError::Unsupported)?;
+        descriptor[..report_descriptor.len()].copy_from_slice(report_descriptor);
+        Ok(report_descriptor.len())
+    }
+}
+
+fn report_ids(device: &MockDevice) -> Vec<u8> {
+    device
+        .written_reports()
+        .iter()
+        .map(|report| report[0])
+        .collect()
+}
+
+#[test]
+fn uses_long_reports_by_default() {
+    let device = MockDevice::new(DeviceType::LitraBeam, "2325FE7044P8");
+    let device_handle = device.open();
+
+    device_handle.set_on(true).unwrap();
+    device_handle.set_brightness_in_lumen(100).unwrap();
+
+    assert!(device.is_on());
+    assert_eq!(device.brightness_in_lumen(), 100);
+    assert_eq!(report_ids(&device), [LONG_REPORT_ID, LONG_REPORT_ID]);
+    assert!(device
+        .written_reports()
+        .iter()
+        .all(|report| report.len() == 20));
+}
+
+#[test]
+fn falls_back_to_short_reports() {
+    let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+    let device_handle = FaultyTransport {
+        reject_long_reports: true,
+        ..FaultyTransport::new(&device)
+    }
+    .open(DeviceType::LitraGlow);
+
+    device_handle.set_on(true).unwrap();
+    device_handle.set_brightness_in_lumen(100).unwrap();
+    assert_eq!(device_handle.brightness_in_lumen().unwrap(), 100);
+
+    assert!(device.is_on());
+    assert_eq!(report_ids(&device), [SHORT_REPORT_ID; 3]);
+    assert!(device
+        .written_reports()
+        .iter()
+        .all(|report| report.len() == 7));
+}
+
+#[test]
+fn uses_the_report_type_from_the_report_descriptor() {
+    let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+    let device_handle = FaultyTransport {
+        report_descriptor: Some(&SHORT_ONLY_DESCRIPTOR),
+        ..FaultyTransport::new(&device)
+    }
+    .open(DeviceType::LitraGlow);
+
+    device_handle.set_on(true).unwrap();
+    assert_eq!(report_ids(&device), [SHORT_REPORT_ID]);
+}
+
+#[test]
+fn doesnt_fall_back_when_the_report_descriptor_only_has_long_reports() {
+    let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+    let device_handle = FaultyTransport {
+        report_descriptor: Some(&LONG_ONLY_DESCRIPTOR),
+        reject_long_reports: true,
+        ..FaultyTransport::new(&device)
+    }
+    .open(DeviceType::LitraGlow);
+
+    assert!(matches!(
+        device_handle.set_on(true),
+        Err(DeviceError::TransportError(_))
+    ));
+    assert!(device.written_reports().is_empty());
+}
+
+#[test]
+fn skips_unrelated_responses() {
+    let device = MockDevice::new(DeviceType::LitraBeamLX, "2325FE7044P8");
+    device.set_brightness_in_lumen(250);
+    let device_handle = FaultyTransport {
+        unrelated_reports: 9,
+        ..FaultyTransport::new(&device)
+    }
+    .open(DeviceType::LitraBeamLX);
+
+    assert_eq!(device_handle.brightness_in_lumen().unwrap(), 250);
+}
+
+#[test]
+fn gives_up_after_too_many_unrelated_responses() {
+    let device = MockDevice::new(DeviceType::LitraBeamLX, "2325FE7044P8");
+    let device_handle = FaultyTransport {
+        unrelated_reports: 10,
+        ..FaultyTransport::new(&device)
+    }
+    .open(DeviceType::LitraBeamLX);
+
+    assert!(matches!(
+        device_handle.brightness_in_lumen(),
+        Err(DeviceError::Unsupported)
+    ));
+}
+
+#[test]
+fn times_out_without_a_response() {
+    let device = MockDevice::new(DeviceType::LitraBeamLX, "2325FE7044P8");
+    let device_handle = device.open();
+
+    // The illumination feature's setters don't respond, so reading a response to one times out
+    assert!(matches!(
+        device_handle.send_raw(ILLUMINATION_FEATURE_INDEX, 0x01, &[0x01]),
+        Err(DeviceError::Timeout)
+    ));
+}
+
+fn retrying_device_handle(
+    device: &MockDevice,
+    failing_writes: usize,
+    retries: u32,
+) -> DeviceHandle {
+    let mut device_handle = FaultyTransport {
+        report_descriptor: Some(&LONG_ONLY_DESCRIPTOR),
+        failing_writes: AtomicUsize::new(failing_writes),
+        ..FaultyTransport::new(device)
+    }
+    .open(DeviceType::LitraGlow);
+    device_handle.set_retry_policy(RetryPolicy {
+        retries,
+        initial_backoff: Duration::from_millis(1),
+        ..RetryPolicy::default()
+    });
+    device_handle
+}
+
+#[test]
+fn retries_failed_writes() {
+    let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+    let device_handle = retrying_device_handle(&device, 2, 2);
+
+    device_handle.set_on(true).unwrap();
+    assert!(device.is_on());
+}
+
+#[test]
+fn limits_the_backoff_between_retries() {
+    let retry_policy = RetryPolicy {
+        retries: 10,
+        ..RetryPolicy::default()
+    };
+    let backoffs: Vec<Duration> = (0..10).map(|retry| retry_policy.backoff(retry)).collect();
+    assert_eq!(
+        backoffs[..5],
+        [100, 200, 400, 800, 1000].map(Duration::from_millis)
+    );
+    assert!(backoffs[5..]
+        .iter()
+        .all(|backoff| *backoff == retry_policy.max_backoff));
+    assert_eq!(retry_policy.backoff(u32::MAX), retry_policy.max_backoff);
+
+    let retry_policy = RetryPolicy {
+        initial_backoff: Duration::MAX,
+        ..RetryPolicy::default()
+    };
+    assert_eq!(retry_policy.backoff(1), retry_policy.max_backoff);
+}
+
+#[test]
+fn gives_up_after_the_last_retry() {
+    let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+    let device_handle = retrying_device_handle(&device, 3, 2);
+
+    assert!(matches!(
+        device_handle.set_on(true),
+        Err(DeviceError::TransportError(_))
+    ));
+    assert!(!device.is_on());
+}
+
+#[test]
+fn doesnt_retry_by_default() {
+    let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+    let device_handle = FaultyTransport {
+        report_descriptor: Some(&LONG_ONLY_DESCRIPTOR),
+        failing_writes: AtomicUsize::new(1),
+        ..FaultyTransport::new(&device)
+    }
+    .open(DeviceType::LitraGlow);
+
+    assert!(device_handle.set_on(true).is_err());
+    device_handle.set_on(true).unwrap();
+}
+
+#[test]
+fn doesnt_retry_invalid_values() {
+    let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+    let device_handle = retrying_device_handle(&device, 0, 2);
+
+    assert!(matches!(
+        device_handle.set_brightness_in_lumen(1000),
+        Err(DeviceError::InvalidBrightness(1000))
+    ));
+    assert!(device.written_reports().is_empty());
+}
+
+#[test]
+fn reads_and_caches_the_firmware_version() {
+    let device = MockDevice::new(DeviceType::LitraBeam, "2325FE7044P8");
+    let device_handle = device.open();
+
+    let firmware_version = device_handle.firmware_version().unwrap();
+    assert_eq!(firmware_version.prefix, "RQM");
+    assert_eq!(firmware_version.number, 0x01);
+    assert_eq!(firmware_version.revision, 0x00);
+    assert_eq!(firmware_version.build, 0x0001);
+    assert_eq!(firmware_version.to_string(), "RQM 01.00.B0001");
+
+    let written_reports = device.written_reports().len();
+    assert_eq!(device_handle.firmware_version().unwrap(), firmware_version);
+    assert_eq!(device.written_reports().len(), written_reports);
+}
+
+#[test]
+fn finds_built_in_descriptors_by_product_id() {
+    let descriptor = DeviceDescriptor::for_product_id(0xc903).unwrap();
+    assert_eq!(descriptor.device_type, DeviceType::LitraBeamLX);
+    assert_eq!(descriptor.product_id, 0xc903);
+    assert_eq!(descriptor.feature_index, 0x06);
+
+    assert_eq!(
+        DeviceDescriptor::for_product_id(0xb901)
+            .unwrap()
+            .device_type,
+        DeviceType::LitraBeam
+    );
+    assert_eq!(DeviceDescriptor::for_product_id(0x1234), None);
+}
+
+#[test]
+fn lists_and_opens_simulated_devices() {
+    let mut context = Litra::new().unwrap();
+    let device = MockDevice::new(DeviceType::LitraBeamLX, "SIMULATED1");
+    context.add_simulated_device(device.clone());
+
+    let simulated_device = context
+        .get_connected_devices()
+        .find(|device| device.serial_number() == Some("SIMULATED1"))
+        .unwrap();
+    assert_eq!(simulated_device.device_type(), DeviceType::LitraBeamLX);
+    assert_eq!(
+        simulated_device.path().to_str().unwrap(),
+        "simulated:SIMULATED1"
+    );
+    assert!(simulated_device.device_info().is_none());
+
+    simulated_device
+        .open(&context)
+        .unwrap()
+        .set_on(true)
+        .unwrap();
+    assert!(device.is_on());
+
+    let owned_device = simulated_device.to_owned();
+    let device_handle = owned_device.open(&context).unwrap();
+    assert!(device_handle.is_on().unwrap());
+}
+
+#[test]
+fn sends_raw_requests() {
+    let device = MockDevice::new(DeviceType::LitraBeamLX, "2325FE7044P8");
+    device.set_on(true);
+    let device_handle = device.open();
+
+    let response = device_handle
+        .send_raw(ILLUMINATION_FEATURE_INDEX, 0x00, &[0x00; 16])
+        .unwrap();
+    assert_eq!(response.error_code(), None);
+    assert_eq!(response.payload()[0], 0x01);
+    assert_eq!(device.written_reports()[0].len(), 20);
+
+    let response = device_handle.send_raw(0x20, 0x00, &[]).unwrap();
+    assert_eq!(response.error_code(), Some(0x06));
+}
+
+#[test]
+fn rejects_invalid_raw_requests() {
+    let device = MockDevice::new(DeviceType::LitraBeamLX, "2325FE7044P8");
+    let device_handle = device.open();
+
+    assert!(matches!(
+        device_handle.send_raw(ILLUMINATION_FEATURE_INDEX, 0x10, &[]),
+        Err(DeviceError::InvalidRawRequest(_))
+    ));
+    assert!(matches!(
+        device_handle.send_raw(ILLUMINATION_FEATURE_INDEX, 0x00, &[0x00; 17]),
+        Err(DeviceError::InvalidRawRequest(_))
+    ));
+    assert!(device.written_reports().is_empty());
+}
+
+#[test]
+fn limits_raw_payloads_for_short_reports() {
+    let device = MockDevice::new(DeviceType::LitraBeamLX, "2325FE7044P8");
+    let device_handle = FaultyTransport {
+        report_descriptor: Some(&SHORT_ONLY_DESCRIPTOR),
+        ..FaultyTransport::new(&device)
+    }
+    .open(DeviceType::LitraBeamLX);
+
+    assert!(matches!(
+        device_handle.send_raw(ILLUMINATION_FEATURE_INDEX, 0x00, &[0x00; 4]),
+        Err(DeviceError::InvalidRawRequest(_))
+    ));
+    assert!(device.written_reports().is_empty());
+
+    device_handle
+        .send_raw(ILLUMINATION_FEATURE_INDEX, 0x00, &[0x00; 3])
+        .unwrap();
+    assert_eq!(report_ids(&device), [SHORT_REPORT_ID]);
+}
+
+#[test]
+fn doesnt_truncate_raw_payloads_when_falling_back() {
+    let device = MockDevice::new(DeviceType::LitraBeamLX, "2325FE7044P8");
+    let device_handle = FaultyTransport {
+        reject_long_reports: true,
+        ..FaultyTransport::new(&device)
+    }
+    .open(DeviceType::LitraBeamLX);
+
+    assert!(device_handle
+        .send_raw(ILLUMINATION_FEATURE_INDEX, 0x00, &[0x01; 4])
+        .is_err());
+    assert!(device.written_reports().is_empty());
+}
+
+#[test]
+fn rejects_descriptors_with_inverted_ranges() {
+    let mut context = Litra::new().unwrap();
+    let descriptor = DeviceDescriptor::for_device_type(DeviceType::LitraBeam);
+    assert!(context.register_device_descriptor(descriptor).is_ok());
+
+    for invalid_descriptor in [
+        DeviceDescriptor {
+            minimum_brightness_in_lumen: descriptor.maximum_brightness_in_lumen + 1,
+            ..descriptor
+        },
+        DeviceDescriptor {
+            minimum_temperature_in_kelvin: descriptor.maximum_temperature_in_kelvin + 100,
+            ..descriptor
+        },
+    ] {
+        assert!(matches!(
+            context.register_device_descriptor(invalid_descriptor),
+            Err(DeviceError::InvalidDeviceDescriptor(_))
+        ));
+    }
+}
+
+#[test]
+fn handles_stop_working_once_disconnected() {
+    let device = MockDevice::new(DeviceType::LitraGlow, "2325FE7044P8");
+    let device_handle = device.open();
+    assert!(!device_handle.is_on().unwrap());
+
+    device.disconnect();
+    assert!(matches!(
+        device_handle.is_on(),
+        Err(DeviceError::NotConnected)
+    ));
+
+    // Like real devices, handles opened before reconnecting stay unusable
+    device.reconnect();
+    assert!(matches!(
+        device_handle.is_on(),
+        Err(DeviceError::NotConnected)
+    ));
+    assert!(!device.open().is_on().unwrap());
+}
+
+#[test]
+fn discovers_brightness_steps_of_devices_which_round_brightness() {
+    let device = MockDevice::new(DeviceType::LitraBeam, "2325FE7044P8");
+    device.set_brightness_rounding_in_lumen(50);
+    let device_handle = device.open();
+    device_handle.set_brightness_in_lumen(200).unwrap();
+
+    let brightness_steps = device_handle.discover_brightness_steps().unwrap();
+    assert_eq!(
+        brightness_steps.steps_in_lumen(),
+        [30, 80, 130, 180, 230, 280, 330, 380]
+    );
+    assert_eq!(brightness_steps.snap(150), 130);
+    assert_eq!(device.brightness_in_lumen(), 180);
+}